@@ -1,13 +1,18 @@
-use std::sync::mpsc::Receiver;
+use std::sync::mpsc::{Receiver, Sender};
 use std::time::{Duration, Instant};
 use tracing::{info, error, warn};
 use rusqlite::Connection;
 use crate::database::player::Player;
+use crate::database::tile_history::{EditQuery, TileEdit};
 use crate::database::world::World;
 
 pub enum DbCommand {
     UpdatePlayer(Player),
     UpdateWorld(World),
+    DeleteWorld(String),
+    LogTileEdit(TileEdit),
+    Flush(Sender<()>),
+    QueryEdits(EditQuery, Sender<Result<Vec<TileEdit>, String>>),
 }
 
 pub fn start_db_thread(rx: Receiver<DbCommand>) {
@@ -49,6 +54,7 @@ pub fn start_db_thread(rx: Receiver<DbCommand>) {
 
     let mut pending_players: Vec<Player> = Vec::new();
     let mut pending_worlds: Vec<World> = Vec::new();
+    let mut pending_edits: Vec<TileEdit> = Vec::new();
     let mut last_flush = Instant::now();
     let flush_interval = Duration::from_millis(200);
     let batch_limit = 100;
@@ -63,6 +69,24 @@ pub fn start_db_thread(rx: Receiver<DbCommand>) {
                 match cmd {
                     DbCommand::UpdatePlayer(p) => pending_players.push(p),
                     DbCommand::UpdateWorld(w) => pending_worlds.push(w),
+                    DbCommand::LogTileEdit(e) => pending_edits.push(e),
+                    DbCommand::DeleteWorld(name) => {
+                        pending_worlds.retain(|w| w.name != name);
+                        flush(&mut conn_players, &mut conn_worlds, &mut pending_players, &mut pending_worlds, &mut pending_edits);
+                        last_flush = Instant::now();
+                        delete_world(&mut conn_worlds, &name);
+                    }
+                    DbCommand::Flush(ack) => {
+                        flush(&mut conn_players, &mut conn_worlds, &mut pending_players, &mut pending_worlds, &mut pending_edits);
+                        last_flush = Instant::now();
+                        ack.send(()).ok();
+                    }
+                    DbCommand::QueryEdits(query, reply) => {
+                        flush(&mut conn_players, &mut conn_worlds, &mut pending_players, &mut pending_worlds, &mut pending_edits);
+                        last_flush = Instant::now();
+                        let result = crate::database::tile_history::run_query_internal(&conn_worlds, &query).map_err(|e| e.to_string());
+                        reply.send(result).ok();
+                    }
                 }
             }
             Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
@@ -70,26 +94,41 @@ pub fn start_db_thread(rx: Receiver<DbCommand>) {
             }
             Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
                 info!("DB Channel disconnected. Flushing remaining and exiting.");
-                flush(&mut conn_players, &mut conn_worlds, &mut pending_players, &mut pending_worlds);
+                flush(&mut conn_players, &mut conn_worlds, &mut pending_players, &mut pending_worlds, &mut pending_edits);
                 break;
             }
         }
 
 
-        if last_flush.elapsed() >= flush_interval || pending_players.len() >= batch_limit || pending_worlds.len() >= batch_limit {
-            flush(&mut conn_players, &mut conn_worlds, &mut pending_players, &mut pending_worlds);
+        if last_flush.elapsed() >= flush_interval || pending_players.len() >= batch_limit || pending_worlds.len() >= batch_limit || pending_edits.len() >= batch_limit {
+            flush(&mut conn_players, &mut conn_worlds, &mut pending_players, &mut pending_worlds, &mut pending_edits);
             last_flush = Instant::now();
         }
     }
 }
 
+fn delete_world(conn_worlds: &mut Connection, name: &str) {
+    let tx = match conn_worlds.transaction() {
+        Ok(t) => t,
+        Err(e) => { error!("Failed to start world transaction: {}", e); return; }
+    };
+    if let Err(e) = crate::database::world::delete_world_internal(&tx, name) {
+        error!("Error deleting world {}: {}", name, e);
+    } else if let Err(e) = tx.commit() {
+        error!("Failed to commit world deletion: {}", e);
+    } else {
+        info!("Deleted world {}", name);
+    }
+}
+
 fn flush(
     conn_players: &mut Connection,
     conn_worlds: &mut Connection,
     pending_players: &mut Vec<Player>,
-    pending_worlds: &mut Vec<World>
+    pending_worlds: &mut Vec<World>,
+    pending_edits: &mut Vec<TileEdit>,
 ) {
-    if pending_players.is_empty() && pending_worlds.is_empty() {
+    if pending_players.is_empty() && pending_worlds.is_empty() && pending_edits.is_empty() {
         return;
     }
 
@@ -125,7 +164,7 @@ fn flush(
     }
 
 
-    if !pending_worlds.is_empty() {
+    if !pending_worlds.is_empty() || !pending_edits.is_empty() {
         let tx = match conn_worlds.transaction() {
             Ok(t) => t,
             Err(e) => { error!("Failed to start world transaction: {}", e); return; }
@@ -139,6 +178,11 @@ fn flush(
                 count += 1;
             }
         }
+        for edit in pending_edits.drain(..) {
+            if let Err(e) = crate::database::tile_history::insert_edit_internal(&tx, &edit) {
+                error!("Error logging tile edit in {}: {}", edit.world, e);
+            }
+        }
 
         if let Err(e) = tx.commit() {
             error!("Failed to commit world batch: {}", e);
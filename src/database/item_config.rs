@@ -8,6 +8,7 @@ pub struct ItemConfig {
     pub id: i32,
     pub clothing_type: u8,
     pub action_type: u8,
+    pub item_category: u8,
     pub hits_to_break: u8,
     pub is_breakable: bool,
     pub is_background: bool,
@@ -16,9 +17,18 @@ pub struct ItemConfig {
     pub visual_effect: u8,
     pub rayman: u16,
     pub punch_options: String,
+    pub restore_time: u32,
+    pub rarity: u16,
+    pub grow_time: u32,
 }
 
+pub const ITEM_CATEGORY_UNTRADEABLE: u8 = 0x80;
+
 impl ItemConfig {
+    pub fn is_untradeable(&self) -> bool {
+        self.id == 18 || self.id == 32 || self.item_category & ITEM_CATEGORY_UNTRADEABLE != 0
+    }
+
     pub fn get_effects(&self) -> ItemEffects {
         let mut effects = ItemEffects::default();
         if self.punch_options.is_empty() {
@@ -79,6 +89,7 @@ fn apply_custom_overrides(m: &mut HashMap<i32, ItemConfig>) {
             id,
             clothing_type: 0,
             action_type: if bg { 1 } else { 0 },
+            item_category: 0,
             hits_to_break: hits,
             is_breakable: breakable,
             is_background: bg,
@@ -87,6 +98,9 @@ fn apply_custom_overrides(m: &mut HashMap<i32, ItemConfig>) {
             visual_effect: 0,
             rayman: 0,
             punch_options: String::new(),
+            restore_time: 0,
+            rarity: 0,
+            grow_time: 0,
         });
     };
 
@@ -123,6 +137,7 @@ pub fn get_item_config(id: i32) -> ItemConfig {
         id,
         clothing_type: 0,
         action_type: 0,
+        item_category: 0,
         hits_to_break: 255,
         is_breakable: false,
         is_background: false,
@@ -131,6 +146,9 @@ pub fn get_item_config(id: i32) -> ItemConfig {
         visual_effect: 0,
         rayman: 0,
         punch_options: String::new(),
+        restore_time: 0,
+        rarity: 0,
+        grow_time: 0,
     })
 }
 
@@ -147,4 +165,20 @@ pub fn get_clothing_type(id: i32) -> Option<usize> {
     } else {
         None
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn untradeable_uses_the_untradeable_category_bit() {
+        let mut config = get_item_config(-1);
+        assert!(!config.is_untradeable());
+        config.item_category = 0x04;
+        assert!(!config.is_untradeable());
+        config.item_category = ITEM_CATEGORY_UNTRADEABLE | 0x04;
+        assert!(config.is_untradeable());
+        assert!(get_item_config(18).is_untradeable());
+        assert!(get_item_config(32).is_untradeable());
+    }
+}
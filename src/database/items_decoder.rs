@@ -12,8 +12,8 @@ pub fn load_item_definitions() -> HashMap<i32, ItemConfig> {
             error!("Failed to load items.dat: {}. Using default hardcoded items.", e);
 
 
-             m.insert(6, ItemConfig { id: 6, clothing_type: 0, action_type: 0, hits_to_break: 255, is_breakable: false, is_background: false, name: "Main Door".to_string(), punch_effect: None, visual_effect: 0, rayman: 0, punch_options: String::new() });
-             m.insert(8, ItemConfig { id: 8, clothing_type: 0, action_type: 0, hits_to_break: 255, is_breakable: false, is_background: false, name: "Bedrock".to_string(), punch_effect: None, visual_effect: 0, rayman: 0, punch_options: String::new() });
+             m.insert(6, ItemConfig { id: 6, clothing_type: 0, action_type: 0, item_category: 0, hits_to_break: 255, is_breakable: false, is_background: false, name: "Main Door".to_string(), punch_effect: None, visual_effect: 0, rayman: 0, punch_options: String::new(), restore_time: 0, rarity: 0, grow_time: 0 });
+             m.insert(8, ItemConfig { id: 8, clothing_type: 0, action_type: 0, item_category: 0, hits_to_break: 255, is_breakable: false, is_background: false, name: "Bedrock".to_string(), punch_effect: None, visual_effect: 0, rayman: 0, punch_options: String::new(), restore_time: 0, rarity: 0, grow_time: 0 });
             return m;
         }
     };
@@ -57,7 +57,7 @@ pub fn load_item_definitions() -> HashMap<i32, ItemConfig> {
         let item_id = read_u32(&data, &mut pos) as i32;
 
         let _editable_type = data[pos]; pos += 1;
-        let _item_category = data[pos]; pos += 1;
+        let item_category = data[pos]; pos += 1;
         let action_type = data[pos]; pos += 1;
         let _hit_sound_type = data[pos]; pos += 1;
 
@@ -75,11 +75,11 @@ pub fn load_item_definitions() -> HashMap<i32, ItemConfig> {
         let break_hits_raw = data[pos]; pos += 1;
         let hits_to_break = if break_hits_raw % 6 == 0 { break_hits_raw / 6 } else { break_hits_raw };
 
-        let _drop_chance = read_u32(&data, &mut pos);
+        let restore_time = read_u32(&data, &mut pos);
 
         let clothing_type = data[pos]; pos += 1;
 
-        let _rarity = read_u16(&data, &mut pos);
+        let rarity = read_u16(&data, &mut pos);
         let _max_amount = data[pos]; pos += 1;
         let _extra_file = read_str(&data, &mut pos, None);
         let _extra_file_hash = read_u32(&data, &mut pos);
@@ -104,7 +104,7 @@ pub fn load_item_definitions() -> HashMap<i32, ItemConfig> {
         let _seed_overlay_color_b = data[pos]; pos += 1;
 
         let _ingredients = read_u32(&data, &mut pos);
-        let _grow_time = read_u32(&data, &mut pos);
+        let grow_time = read_u32(&data, &mut pos);
         let _val2 = read_u16(&data, &mut pos);
         let rayman = read_u16(&data, &mut pos);
         let _extra_options = read_str(&data, &mut pos, None);
@@ -157,6 +157,7 @@ pub fn load_item_definitions() -> HashMap<i32, ItemConfig> {
             id: item_id,
             clothing_type,
             action_type,
+            item_category,
             hits_to_break,
             is_breakable: hits_to_break > 0,
             is_background: action_type == 1,
@@ -165,6 +166,9 @@ pub fn load_item_definitions() -> HashMap<i32, ItemConfig> {
             visual_effect,
             rayman,
             punch_options,
+            restore_time,
+            rarity,
+            grow_time,
         });
     }
 
@@ -2,4 +2,8 @@ pub mod player;
 pub mod world;
 pub mod item_config;
 pub mod items_decoder;
-pub mod db_thread;
\ No newline at end of file
+pub mod db_thread;
+pub mod tile_extra;
+pub mod tile_history;
+pub mod schematic;
+pub mod world_io;
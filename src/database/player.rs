@@ -1,6 +1,10 @@
 use rusqlite::{params, Connection, Result};
 use tracing::info;
 
+pub const MAX_STACK: i32 = 200;
+pub const MAX_RECENT_WORLDS: usize = 6;
+pub const ROLE_ADMIN: i32 = 10;
+
 #[derive(Debug, Clone)]
 pub struct InventorySlot {
     pub item_id: i32,
@@ -29,6 +33,7 @@ pub struct Player {
     pub adventurer_lvl: i32,
     pub adventurer_xp: i32,
     pub punch_id: u8,
+    pub recent_worlds: Vec<String>,
 }
 
 impl Player {
@@ -53,7 +58,44 @@ impl Player {
             miner_lvl: 1, miner_xp: 0,
             adventurer_lvl: 1, adventurer_xp: 0,
             punch_id: 0,
+            recent_worlds: Vec::new(),
+        }
+    }
+
+    pub fn item_count(&self, item_id: i32) -> i32 {
+        self.slots.iter().find(|s| s.item_id == item_id).map_or(0, |s| s.count)
+    }
+
+    pub fn remove_item(&mut self, item_id: i32, count: i32) -> i32 {
+        let Some(index) = self.slots.iter().position(|s| s.item_id == item_id) else { return 0; };
+        let removed = count.clamp(0, self.slots[index].count);
+        self.slots[index].count -= removed;
+        if self.slots[index].count <= 0 {
+            self.slots.remove(index);
+        }
+        removed
+    }
+
+    pub fn add_item(&mut self, item_id: i32, count: i32) -> i32 {
+        if count <= 0 { return 0; }
+        if let Some(slot) = self.slots.iter_mut().find(|s| s.item_id == item_id) {
+            let added = count.min(MAX_STACK - slot.count).max(0);
+            slot.count += added;
+            return added;
         }
+        let added = count.min(MAX_STACK);
+        self.slots.push(InventorySlot { item_id, count: added });
+        added
+    }
+
+    pub fn is_admin(&self) -> bool {
+        self.role >= ROLE_ADMIN
+    }
+
+    pub fn visit_world(&mut self, world_name: &str) {
+        self.recent_worlds.retain(|w| !w.eq_ignore_ascii_case(world_name));
+        self.recent_worlds.insert(0, world_name.to_uppercase());
+        self.recent_worlds.truncate(MAX_RECENT_WORLDS);
     }
 }
 
@@ -119,6 +161,16 @@ pub fn init_db() -> Result<()> {
 
     let _ = conn.execute("ALTER TABLE equip ADD COLUMN s INTEGER", []);
 
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS recent_worlds (
+            _n TEXT,
+            w TEXT,
+            s INTEGER,
+            FOREIGN KEY(_n) REFERENCES peers(_n)
+        )",
+        [],
+    )?;
+
     info!("Database initialized successfully");
     Ok(())
 }
@@ -183,6 +235,13 @@ pub fn load_player(name: &str) -> Result<Option<Player>> {
 
         if player.equipped.len() < 10 { player.equipped.resize(10, 0); }
 
+
+        let mut recent_stmt = conn.prepare("SELECT w FROM recent_worlds WHERE _n = ? ORDER BY s")?;
+        let recent_rows = recent_stmt.query_map(params![name], |r| r.get::<_, String>(0))?;
+        for world_name in recent_rows {
+            player.recent_worlds.push(world_name?);
+        }
+
         Ok(Some(player))
     } else {
         Ok(None)
@@ -242,6 +301,15 @@ pub fn save_player_internal(tx: &rusqlite::Transaction, player: &Player) -> Resu
             )?;
         }
     }
+
+
+    tx.execute("DELETE FROM recent_worlds WHERE _n = ?", params![player.name])?;
+    for (order, world_name) in player.recent_worlds.iter().enumerate() {
+        tx.execute(
+            "INSERT INTO recent_worlds (_n, w, s) VALUES (?, ?, ?)",
+            params![player.name, world_name, order as i32],
+        )?;
+    }
     Ok(())
 }
 
@@ -286,4 +354,39 @@ impl OptionalRow for Result<String, rusqlite::Error> {
             Err(e) => Err(e),
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_item_stacks_up_to_the_limit() {
+        let mut player = Player::new("tester");
+        assert_eq!(player.add_item(2, 150), 150);
+        assert_eq!(player.add_item(2, 100), MAX_STACK - 150);
+        assert_eq!(player.item_count(2), MAX_STACK);
+        assert_eq!(player.add_item(4, 500), MAX_STACK);
+        assert_eq!(player.item_count(4), MAX_STACK);
+    }
+
+    #[test]
+    fn add_item_ignores_empty_amounts() {
+        let mut player = Player::new("tester");
+        let slots = player.slots.len();
+        assert_eq!(player.add_item(2, 0), 0);
+        assert_eq!(player.add_item(2, -5), 0);
+        assert_eq!(player.slots.len(), slots);
+    }
+
+    #[test]
+    fn remove_item_clears_empty_slots() {
+        let mut player = Player::new("tester");
+        player.add_item(2, 10);
+        assert_eq!(player.remove_item(2, -1), 0);
+        assert_eq!(player.remove_item(2, 4), 4);
+        assert_eq!(player.item_count(2), 6);
+        assert_eq!(player.remove_item(2, 50), 6);
+        assert!(player.slots.iter().all(|s| s.item_id != 2));
+        assert_eq!(player.remove_item(2, 1), 0);
+    }
+}
@@ -0,0 +1,106 @@
+use std::path::PathBuf;
+use anyhow::{bail, Result};
+use bytes::BufMut;
+use crate::database::world::BinReader;
+
+const SCHEMATIC_MAGIC: &[u8; 4] = b"GTSC";
+const SCHEMATIC_VERSION: u16 = 1;
+
+pub const SCHEMATIC_DIR: &str = "schematics";
+pub const SCHEMATIC_EXTENSION: &str = "gtschem";
+pub const MAX_SCHEMATIC_NAME_LEN: usize = 32;
+pub const MAX_SCHEMATIC_TILES: usize = (crate::game::worldgen::MAX_WIDTH * crate::game::worldgen::MAX_HEIGHT) as usize;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schematic {
+    pub width: u32,
+    pub height: u32,
+    pub tiles: Vec<(u16, u16)>,
+}
+
+impl Schematic {
+    pub fn get(&self, x: u32, y: u32) -> Option<(u16, u16)> {
+        if x >= self.width || y >= self.height { return None; }
+        self.tiles.get((y * self.width + x) as usize).copied()
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut buf = Vec::with_capacity(14 + self.tiles.len() * 4);
+        buf.put_slice(SCHEMATIC_MAGIC);
+        buf.put_u16_le(SCHEMATIC_VERSION);
+        buf.put_u32_le(self.width);
+        buf.put_u32_le(self.height);
+        for &(fg, bg) in &self.tiles {
+            buf.put_u16_le(fg);
+            buf.put_u16_le(bg);
+        }
+        Ok(zstd::encode_all(&buf[..], 3)?)
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let data = zstd::decode_all(data)?;
+        let mut r = BinReader::new(&data);
+        if r.bytes(4)? != SCHEMATIC_MAGIC { bail!("not a schematic file"); }
+        let version = r.u16()?;
+        if version != SCHEMATIC_VERSION { bail!("unsupported schematic version {}", version); }
+
+        let width = r.u32()?;
+        let height = r.u32()?;
+        let count = width as usize * height as usize;
+        if width == 0 || height == 0 || count > MAX_SCHEMATIC_TILES {
+            bail!("invalid schematic size {}x{}", width, height);
+        }
+
+        let mut tiles = Vec::with_capacity(count);
+        for _ in 0..count {
+            tiles.push((r.u16()?, r.u16()?));
+        }
+        if !r.is_empty() { bail!("trailing data after schematic tiles"); }
+        Ok(Self { width, height, tiles })
+    }
+}
+
+pub fn is_valid_file_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_SCHEMATIC_NAME_LEN
+        && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-')
+}
+
+pub fn file_path(name: &str) -> Option<PathBuf> {
+    is_valid_file_name(name).then(|| PathBuf::from(SCHEMATIC_DIR).join(format!("{}.{}", name.to_lowercase(), SCHEMATIC_EXTENSION)))
+}
+
+pub fn save(name: &str, schematic: &Schematic) -> Result<PathBuf> {
+    let Some(path) = file_path(name) else { bail!("invalid schematic name {}", name); };
+    std::fs::create_dir_all(SCHEMATIC_DIR)?;
+    std::fs::write(&path, schematic.to_bytes()?)?;
+    Ok(path)
+}
+
+pub fn load(name: &str) -> Result<Schematic> {
+    let Some(path) = file_path(name) else { bail!("invalid schematic name {}", name); };
+    Schematic::from_bytes(&std::fs::read(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schematic_round_trips() {
+        let schematic = Schematic { width: 3, height: 2, tiles: vec![(2, 14), (0, 0), (10, 14), (8, 0), (0, 14), (2, 2)] };
+        let bytes = schematic.to_bytes().unwrap();
+        assert_eq!(Schematic::from_bytes(&bytes).unwrap(), schematic);
+        assert_eq!(schematic.get(0, 1), Some((8, 0)));
+        assert_eq!(schematic.get(3, 0), None);
+        assert!(Schematic::from_bytes(b"garbage").is_err());
+    }
+
+    #[test]
+    fn schematic_names_are_sanitized() {
+        assert_eq!(file_path("Spawn_1"), Some(PathBuf::from("schematics/spawn_1.gtschem")));
+        assert_eq!(file_path("../worlds"), None);
+        assert_eq!(file_path(""), None);
+        assert_eq!(file_path(&"a".repeat(MAX_SCHEMATIC_NAME_LEN + 1)), None);
+    }
+}
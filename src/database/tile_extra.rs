@@ -0,0 +1,252 @@
+use anyhow::{bail, Result};
+use bytes::BufMut;
+use serde::{Serialize, Deserialize};
+use crate::database::world::{put_string, BinReader};
+
+const EXTRA_DOOR: u8 = 1;
+const EXTRA_SIGN: u8 = 2;
+const EXTRA_LOCK: u8 = 3;
+const EXTRA_SEED: u8 = 4;
+const EXTRA_MAILBOX: u8 = 6;
+const EXTRA_BULLETIN: u8 = 7;
+const EXTRA_DISPLAY_BOX: u8 = 23;
+const EXTRA_VENDING: u8 = 24;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TileExtra {
+    Door { label: String, destination: String, door_id: String, locked: bool },
+    Sign { text: String },
+    Lock { flags: u8, owner_name: String, admins: Vec<String>, minimum_level: u8 },
+    Seed { planted_at: u64, fruit_count: u8 },
+    Mailbox { letters: Vec<String> },
+    Bulletin { notes: Vec<String> },
+    DisplayBox { item_id: u32 },
+    Vending { item_id: u32, price: i32, stock: u32 },
+}
+
+impl TileExtra {
+    pub fn for_action_type(action_type: u8) -> Option<Self> {
+        match action_type {
+            2 | 13 | 26 => Some(TileExtra::Door {
+                label: String::new(),
+                destination: String::new(),
+                door_id: String::new(),
+                locked: false,
+            }),
+            10 => Some(TileExtra::Sign { text: String::new() }),
+            3 => Some(TileExtra::Lock {
+                flags: 0,
+                owner_name: String::new(),
+                admins: Vec::new(),
+                minimum_level: 0,
+            }),
+            19 => Some(TileExtra::Seed { planted_at: 0, fruit_count: 0 }),
+            33 => Some(TileExtra::Mailbox { letters: Vec::new() }),
+            34 => Some(TileExtra::Bulletin { notes: Vec::new() }),
+            61 => Some(TileExtra::DisplayBox { item_id: 0 }),
+            62 => Some(TileExtra::Vending { item_id: 0, price: 0, stock: 0 }),
+            _ => None,
+        }
+    }
+
+    pub fn type_id(&self) -> u8 {
+        match self {
+            TileExtra::Door { .. } => EXTRA_DOOR,
+            TileExtra::Sign { .. } => EXTRA_SIGN,
+            TileExtra::Lock { .. } => EXTRA_LOCK,
+            TileExtra::Seed { .. } => EXTRA_SEED,
+            TileExtra::Mailbox { .. } => EXTRA_MAILBOX,
+            TileExtra::Bulletin { .. } => EXTRA_BULLETIN,
+            TileExtra::DisplayBox { .. } => EXTRA_DISPLAY_BOX,
+            TileExtra::Vending { .. } => EXTRA_VENDING,
+        }
+    }
+
+    pub fn write_client(&self, buf: &mut Vec<u8>, now: u64) {
+        buf.put_u8(self.type_id());
+        match self {
+            TileExtra::Door { label, locked, .. } => {
+                put_string(buf, label);
+                buf.put_u8(if *locked { 0x08 } else { 0 });
+            }
+            TileExtra::Sign { text } => {
+                put_string(buf, text);
+                buf.put_i32_le(-1);
+            }
+            TileExtra::Lock { flags, minimum_level, .. } => {
+                buf.put_u8(*flags);
+                buf.put_u32_le(0);
+                buf.put_u32_le(0);
+                buf.put_u8(*minimum_level);
+                buf.extend_from_slice(&[0; 7]);
+            }
+            TileExtra::Seed { planted_at, fruit_count } => {
+                buf.put_u32_le(now.saturating_sub(*planted_at) as u32);
+                buf.put_u8(*fruit_count);
+            }
+            TileExtra::Mailbox { letters: entries } | TileExtra::Bulletin { notes: entries } => {
+                put_string(buf, "");
+                put_string(buf, "");
+                put_string(buf, "");
+                buf.put_u8(!entries.is_empty() as u8);
+            }
+            TileExtra::DisplayBox { item_id } => {
+                buf.put_u32_le(*item_id);
+            }
+            TileExtra::Vending { item_id, price, .. } => {
+                buf.put_u32_le(*item_id);
+                buf.put_i32_le(*price);
+            }
+        }
+    }
+
+    pub fn write(&self, buf: &mut Vec<u8>) {
+        buf.put_u8(self.type_id());
+        match self {
+            TileExtra::Door { label, destination, door_id, locked } => {
+                put_string(buf, label);
+                put_string(buf, destination);
+                put_string(buf, door_id);
+                buf.put_u8(*locked as u8);
+            }
+            TileExtra::Sign { text } => put_string(buf, text),
+            TileExtra::Lock { flags, owner_name, admins, minimum_level } => {
+                buf.put_u8(*flags);
+                put_string(buf, owner_name);
+                put_strings(buf, admins);
+                buf.put_u8(*minimum_level);
+            }
+            TileExtra::Seed { planted_at, fruit_count } => {
+                buf.put_u64_le(*planted_at);
+                buf.put_u8(*fruit_count);
+            }
+            TileExtra::Mailbox { letters } => put_strings(buf, letters),
+            TileExtra::Bulletin { notes } => put_strings(buf, notes),
+            TileExtra::DisplayBox { item_id } => buf.put_u32_le(*item_id),
+            TileExtra::Vending { item_id, price, stock } => {
+                buf.put_u32_le(*item_id);
+                buf.put_i32_le(*price);
+                buf.put_u32_le(*stock);
+            }
+        }
+    }
+
+    pub fn read(reader: &mut BinReader<'_>) -> Result<Self> {
+        let extra = match reader.u8()? {
+            EXTRA_DOOR => TileExtra::Door {
+                label: reader.string()?,
+                destination: reader.string()?,
+                door_id: reader.string()?,
+                locked: reader.u8()? != 0,
+            },
+            EXTRA_SIGN => TileExtra::Sign { text: reader.string()? },
+            EXTRA_LOCK => TileExtra::Lock {
+                flags: reader.u8()?,
+                owner_name: reader.string()?,
+                admins: read_strings(reader)?,
+                minimum_level: reader.u8()?,
+            },
+            EXTRA_SEED => TileExtra::Seed { planted_at: reader.u64()?, fruit_count: reader.u8()? },
+            EXTRA_MAILBOX => TileExtra::Mailbox { letters: read_strings(reader)? },
+            EXTRA_BULLETIN => TileExtra::Bulletin { notes: read_strings(reader)? },
+            EXTRA_DISPLAY_BOX => TileExtra::DisplayBox { item_id: reader.u32()? },
+            EXTRA_VENDING => TileExtra::Vending {
+                item_id: reader.u32()?,
+                price: reader.i32()?,
+                stock: reader.u32()?,
+            },
+            t => bail!("unknown tile extra type {}", t),
+        };
+        Ok(extra)
+    }
+}
+
+fn put_strings(buf: &mut Vec<u8>, values: &[String]) {
+    buf.put_u16_le(values.len() as u16);
+    for value in values {
+        put_string(buf, value);
+    }
+}
+
+fn read_strings(reader: &mut BinReader<'_>) -> Result<Vec<String>> {
+    let count = reader.u16()? as usize;
+    let mut values = Vec::with_capacity(count.min(64));
+    for _ in 0..count {
+        values.push(reader.string()?);
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_variants() -> Vec<TileExtra> {
+        vec![
+            TileExtra::Door {
+                label: "EXIT".to_string(),
+                destination: "START".to_string(),
+                door_id: "A1".to_string(),
+                locked: true,
+            },
+            TileExtra::Sign { text: "hello | world".to_string() },
+            TileExtra::Lock {
+                flags: 0x80,
+                owner_name: "owner".to_string(),
+                admins: vec!["a".to_string(), "b".to_string()],
+                minimum_level: 10,
+            },
+            TileExtra::Seed { planted_at: 1_700_000_000, fruit_count: 3 },
+            TileExtra::Mailbox { letters: vec!["hi".to_string()] },
+            TileExtra::Bulletin { notes: Vec::new() },
+            TileExtra::DisplayBox { item_id: 242 },
+            TileExtra::Vending { item_id: 2, price: -5, stock: 200 },
+        ]
+    }
+
+    #[test]
+    fn storage_round_trip_per_variant() {
+        for extra in all_variants() {
+            let mut buf = Vec::new();
+            extra.write(&mut buf);
+            let mut reader = BinReader::new(&buf);
+            assert_eq!(TileExtra::read(&mut reader).unwrap(), extra);
+            assert!(reader.is_empty(), "trailing bytes for {:?}", extra);
+        }
+    }
+
+    #[test]
+    fn storage_truncation_is_an_error() {
+        for extra in all_variants() {
+            let mut buf = Vec::new();
+            extra.write(&mut buf);
+            for len in 0..buf.len() {
+                assert!(TileExtra::read(&mut BinReader::new(&buf[..len])).is_err(), "{:?} at {}", extra, len);
+            }
+        }
+    }
+
+    #[test]
+    fn client_encoding_per_variant() {
+        let expected_len = [1 + 6 + 1, 1 + 15 + 4, 1 + 1 + 4 + 4 + 8, 1 + 4 + 1, 1 + 6 + 1, 1 + 6 + 1, 1 + 4, 1 + 8];
+        for (extra, len) in all_variants().iter().zip(expected_len) {
+            let mut buf = Vec::new();
+            extra.write_client(&mut buf, 1_700_000_060);
+            assert_eq!(buf[0], extra.type_id());
+            assert_eq!(buf.len(), len, "{:?}", extra);
+        }
+
+        let mut buf = Vec::new();
+        TileExtra::Seed { planted_at: 1_700_000_000, fruit_count: 3 }.write_client(&mut buf, 1_700_000_060);
+        assert_eq!(&buf[1..5], &60u32.to_le_bytes());
+    }
+
+    #[test]
+    fn defaults_follow_action_types() {
+        assert!(matches!(TileExtra::for_action_type(13), Some(TileExtra::Door { .. })));
+        assert!(matches!(TileExtra::for_action_type(10), Some(TileExtra::Sign { .. })));
+        assert!(matches!(TileExtra::for_action_type(3), Some(TileExtra::Lock { .. })));
+        assert!(matches!(TileExtra::for_action_type(19), Some(TileExtra::Seed { .. })));
+        assert_eq!(TileExtra::for_action_type(17), None);
+    }
+}
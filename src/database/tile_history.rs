@@ -0,0 +1,85 @@
+use rusqlite::{params, Connection, Result};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileEdit {
+    pub world: String,
+    pub x: u32,
+    pub y: u32,
+    pub player: String,
+    pub at: u64,
+    pub before: (u16, u16),
+    pub after: (u16, u16),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditQuery {
+    Tile { world: String, x: u32, y: u32, limit: usize },
+    PlayerSince { world: String, player: String, since: u64 },
+}
+
+pub fn init_db() -> Result<()> {
+    let conn = Connection::open("db/worlds.db")?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS tile_edits (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            world TEXT,
+            x INTEGER,
+            y INTEGER,
+            player TEXT,
+            at INTEGER,
+            fg_before INTEGER,
+            bg_before INTEGER,
+            fg_after INTEGER,
+            bg_after INTEGER
+        )",
+        [],
+    )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS tile_edits_tile ON tile_edits (world, x, y)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS tile_edits_player ON tile_edits (world, player, at)", [])?;
+    Ok(())
+}
+
+pub fn insert_edit_internal(tx: &rusqlite::Transaction, edit: &TileEdit) -> Result<()> {
+    tx.execute(
+        "INSERT INTO tile_edits (world, x, y, player, at, fg_before, bg_before, fg_after, bg_after) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        params![edit.world, edit.x, edit.y, edit.player, edit.at as i64, edit.before.0, edit.before.1, edit.after.0, edit.after.1],
+    )?;
+    Ok(())
+}
+
+fn read_edit(row: &rusqlite::Row<'_>) -> Result<TileEdit> {
+    Ok(TileEdit {
+        world: row.get(0)?,
+        x: row.get(1)?,
+        y: row.get(2)?,
+        player: row.get(3)?,
+        at: row.get::<_, i64>(4)? as u64,
+        before: (row.get(5)?, row.get(6)?),
+        after: (row.get(7)?, row.get(8)?),
+    })
+}
+
+pub fn run_query_internal(conn: &Connection, query: &EditQuery) -> Result<Vec<TileEdit>> {
+    match query {
+        EditQuery::Tile { world, x, y, limit } => history_at(conn, world, *x, *y, *limit),
+        EditQuery::PlayerSince { world, player, since } => edits_by_player_since(conn, world, player, *since),
+    }
+}
+
+fn history_at(conn: &Connection, world: &str, x: u32, y: u32, limit: usize) -> Result<Vec<TileEdit>> {
+    let mut stmt = conn.prepare(
+        "SELECT world, x, y, player, at, fg_before, bg_before, fg_after, bg_after FROM tile_edits
+         WHERE world = ? AND x = ? AND y = ? ORDER BY id DESC LIMIT ?",
+    )?;
+    let edits = stmt.query_map(params![world, x, y, limit as i64], read_edit)?;
+    edits.collect()
+}
+
+fn edits_by_player_since(conn: &Connection, world: &str, player: &str, since: u64) -> Result<Vec<TileEdit>> {
+    let mut stmt = conn.prepare(
+        "SELECT world, x, y, player, at, fg_before, bg_before, fg_after, bg_after FROM tile_edits
+         WHERE world = ? AND player = ? COLLATE NOCASE AND at >= ? ORDER BY id",
+    )?;
+    let edits = stmt.query_map(params![world, player, since as i64], read_edit)?;
+    edits.collect()
+}
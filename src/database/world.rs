@@ -1,8 +1,26 @@
+use std::collections::{BTreeMap, HashMap};
 use std::time::{SystemTime, UNIX_EPOCH};
-use tracing::{info, error};
-use bytes::{Buf, BufMut, BytesMut};
-use anyhow::Result;
+use tracing::{info, warn};
+use bytes::BufMut;
+use anyhow::{bail, Result};
 use serde::{Serialize, Deserialize};
+use crate::database::tile_extra::TileExtra;
+use crate::game::locks::{self, LockKind};
+
+const WORLD_FORMAT_VERSION: i32 = 3;
+
+const SECTION_END: u8 = 0;
+const SECTION_NPCS: u8 = 1;
+const SECTION_LOCK_AREAS: u8 = 2;
+const SECTION_OBJECTS: u8 = 3;
+const SECTION_SETTINGS: u8 = 4;
+const SECTION_BANS: u8 = 5;
+
+const SETTING_NO_PUNCH: u8 = 0x01;
+const SETTING_ZOMBIE: u8 = 0x02;
+
+pub const MAX_OBJECTS: usize = 2000;
+pub const DEFAULT_WEATHER: u16 = 41;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Tile {
@@ -11,7 +29,8 @@ pub struct Tile {
     pub state3: u8,
     pub state4: u8,
     pub hits: u8,
-    pub label: String,
+    #[serde(default)]
+    pub extra: Option<TileExtra>,
     pub last_tick: u64,
 }
 
@@ -23,12 +42,43 @@ impl Tile {
             state3: 0,
             state4: 0,
             hits: 0,
-            label: String::new(),
+            extra: None,
             last_tick: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
         }
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorldObject {
+    pub uid: u32,
+    pub item_id: u16,
+    pub count: u8,
+    pub flags: u8,
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorldSettings {
+    pub no_punch: bool,
+    pub zombie: bool,
+    pub music: String,
+    pub weather: u16,
+    pub description: String,
+}
+
+impl Default for WorldSettings {
+    fn default() -> Self {
+        Self {
+            no_punch: false,
+            zombie: false,
+            music: String::new(),
+            weather: DEFAULT_WEATHER,
+            description: String::new(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct World {
     pub name: String,
@@ -39,6 +89,20 @@ pub struct World {
     pub tiles: Vec<Tile>,
     #[serde(default)]
     pub npcs: Vec<crate::game::npc::Npc>,
+    #[serde(default)]
+    pub lock_areas: BTreeMap<u32, Vec<u32>>,
+    #[serde(default)]
+    pub objects: Vec<WorldObject>,
+    #[serde(default)]
+    pub last_object_uid: u32,
+    #[serde(default)]
+    pub visits: u32,
+    #[serde(default)]
+    pub settings: WorldSettings,
+    #[serde(default)]
+    pub bans: BTreeMap<String, u64>,
+    #[serde(skip)]
+    pub damage_times: HashMap<u32, u64>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -49,65 +113,27 @@ pub enum TileChangeResult {
     PlacedFG(u16),
     PlacedBG(u16),
     Damaged(u16, u8),
+    Harvested(u16, u8),
+    Locked,
 }
 
 impl World {
-    pub fn new(name: &str) -> Self {
-        let mut world = Self {
+    pub fn blank(name: &str, width: u32, height: u32) -> Self {
+        Self {
             name: name.to_uppercase(),
-            width: 100,
-            height: 60,
+            width,
+            height,
             owner_name: String::new(),
             owner: 0,
-            tiles: vec![Tile::new(0, 0); 6000],
+            tiles: vec![Tile::new(0, 0); (width * height) as usize],
             npcs: Vec::new(),
-        };
-        world.generate();
-        world
-    }
-
-    pub fn generate(&mut self) {
-        use rand::Rng;
-        let mut rng = rand::thread_rng();
-        let main_door_x = rng.gen_range(2..98);
-
-        for y in 0..60 {
-            for x in 0..100 {
-                let i = (y * 100 + x) as usize;
-
-
-                if y >= 37 {
-                    self.tiles[i].bg = 14;
-
-                    if y >= 38 && y < 50 {
-
-                        if rng.gen_ratio(1, 38) {
-                            self.tiles[i].fg = 10;
-                        } else {
-                            self.tiles[i].fg = 2;
-                        }
-                    } else if y >= 50 && y < 54 {
-
-                        if rng.gen_ratio(3, 8) {
-                            self.tiles[i].fg = 4;
-                        } else {
-                            self.tiles[i].fg = 2;
-                        }
-                    } else if y >= 54 {
-                        self.tiles[i].fg = 8;
-                    } else {
-                        self.tiles[i].fg = 2;
-                    }
-                }
-
-
-                if x == main_door_x && y == 36 {
-                    self.tiles[i].fg = 6;
-                    self.tiles[i].label = "EXIT".to_string();
-                } else if x == main_door_x && y == 37 {
-                    self.tiles[i].fg = 8;
-                }
-            }
+            lock_areas: BTreeMap::new(),
+            objects: Vec::new(),
+            last_object_uid: 0,
+            visits: 0,
+            settings: WorldSettings::default(),
+            bans: BTreeMap::new(),
+            damage_times: HashMap::new(),
         }
     }
 
@@ -132,86 +158,163 @@ impl World {
         buf.extend_from_slice(&[0; 5]);
 
 
-        for (_i, tile) in self.tiles.iter().enumerate() {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        for tile in &self.tiles {
+            write_client_tile(&mut buf, tile, now);
+        }
 
-            let mut flags = tile.state3 as u16 | ((tile.state4 as u16) << 8);
 
+        buf.extend_from_slice(&[0; 12]);
+        buf.put_u32_le(self.objects.len() as u32);
+        buf.put_u32_le(self.last_object_uid);
+        for object in &self.objects {
+            buf.put_u16_le(object.item_id);
+            buf.put_f32_le(object.x);
+            buf.put_f32_le(object.y);
+            buf.put_u8(object.count);
+            buf.put_u8(object.flags);
+            buf.put_u32_le(object.uid);
+        }
 
-            let config = crate::database::item_config::get_item_config(tile.fg as i32);
-            let action_type = config.action_type;
 
+        buf.put_u16_le(self.settings.weather);
+        buf.put_u16_le(0);
+        buf.put_u32_le(0);
+        buf.put_u32_le(0);
 
+        buf
+    }
 
-            let has_extra_data = matches!(action_type, 2 | 3 | 10 | 13 | 19 | 26 | 33 | 34);
+    pub fn tile_to_bytes(&self, index: usize) -> Vec<u8> {
+        let mut buf = Vec::new();
+        if let Some(tile) = self.tiles.get(index) {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+            write_client_tile(&mut buf, tile, now);
+        }
+        buf
+    }
 
-            if has_extra_data {
-                flags |= 0x0001;
-            }
+    pub fn spawn_object(&mut self, item_id: u16, count: u8, x: f32, y: f32) -> Option<WorldObject> {
+        if count == 0 || self.objects.len() >= MAX_OBJECTS { return None; }
+        self.last_object_uid += 1;
+        let object = WorldObject { uid: self.last_object_uid, item_id, count, flags: 0, x, y };
+        self.objects.push(object.clone());
+        Some(object)
+    }
 
-            buf.put_u16_le(tile.fg);
-            buf.put_u16_le(tile.bg);
-            buf.put_u16_le(0);
-            buf.put_u16_le(flags);
-
-            if has_extra_data {
-                match action_type {
-                    2 | 26 => {
-                        buf.put_u8(0x01);
-                        buf.put_u16_le(tile.label.len() as u16);
-                        buf.extend_from_slice(tile.label.as_bytes());
-                        buf.put_u8(0);
-                    }
-                     13 => {
-                        buf.put_u8(0x01);
-                        buf.put_u16_le(tile.label.len() as u16);
-                        buf.extend_from_slice(tile.label.as_bytes());
-                        buf.put_u8(0);
-                    }
-                    10 => {
-                        buf.put_u8(0x02);
-                        buf.put_u16_le(tile.label.len() as u16);
-                        buf.extend_from_slice(tile.label.as_bytes());
-                        buf.put_i32_le(-1);
-                    }
-                    3 => {
+    pub fn find_object(&self, uid: u32) -> Option<usize> {
+        self.objects.iter().position(|o| o.uid == uid)
+    }
 
-                         buf.put_u8(0x03);
-                         buf.put_u8(0);
-                         buf.put_u32_le(0);
-                         buf.put_u32_le(0);
+    pub fn world_lock(&self) -> Option<usize> {
+        self.tiles.iter().position(|t| locks::lock_kind(t.fg) == Some(LockKind::World))
+    }
 
-                         buf.extend_from_slice(&[0; 8]);
-                    }
-                    19 => {
-                        buf.put_u8(0x04);
-                        buf.put_u32_le(0);
-                        buf.put_u8(0);
-                    }
-                    33 | 34 => {
+    pub fn area_lock_at(&self, index: usize) -> Option<usize> {
+        self.lock_areas.iter()
+            .find(|(_, area)| area.binary_search(&(index as u32)).is_ok())
+            .map(|(&lock, _)| lock as usize)
+    }
 
-                         buf.put_u8(0x0);
-                    }
-                    _ => {
+    fn governing_lock(&self, index: usize) -> Option<&TileExtra> {
+        let lock = self.area_lock_at(index).or_else(|| self.world_lock())?;
+        self.tiles[lock].extra.as_ref()
+    }
+
+    pub fn lock_owner(&self, index: usize) -> Option<&str> {
+        match self.governing_lock(index)? {
+            TileExtra::Lock { owner_name, .. } => Some(owner_name),
+            _ => None,
+        }
+    }
 
+    pub fn can_build(&self, index: usize, player_name: &str) -> bool {
+        self.governing_lock(index).is_none_or(|lock| locks::allows(lock, player_name))
+    }
 
+    pub fn minimum_level(&self) -> u8 {
+        match self.world_lock().and_then(|i| self.tiles[i].extra.as_ref()) {
+            Some(TileExtra::Lock { minimum_level, .. }) => *minimum_level,
+            _ => 0,
+        }
+    }
 
-                    }
-                }
+    pub fn has_access(&self, player_name: &str) -> bool {
+        match self.world_lock().and_then(|i| self.tiles[i].extra.as_ref()) {
+            Some(TileExtra::Lock { owner_name, admins, .. }) => {
+                owner_name.eq_ignore_ascii_case(player_name) || admins.iter().any(|a| a.eq_ignore_ascii_case(player_name))
             }
+            _ => false,
         }
+    }
 
+    pub fn restore_tile(&mut self, index: usize, (fg, bg): (u16, u16)) -> bool {
+        let Some(tile) = self.tiles.get(index) else { return false; };
+        if (tile.fg, tile.bg) == (fg, bg) { return false; }
+        if locks::lock_kind(tile.fg).is_some() || locks::lock_kind(fg).is_some() { return false; }
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let tile = &mut self.tiles[index];
+        if tile.fg != fg {
+            let config = crate::database::item_config::get_item_config(fg as i32);
+            tile.extra = if fg == 0 {
+                None
+            } else if config.action_type == crate::game::farming::SEED_ACTION_TYPE {
+                tile.last_tick = now;
+                Some(crate::game::farming::plant(now))
+            } else {
+                TileExtra::for_action_type(config.action_type)
+            };
+        }
+        tile.fg = fg;
+        tile.bg = bg;
+        tile.hits = 0;
+        self.damage_times.remove(&(index as u32));
+        true
+    }
 
-        buf.extend_from_slice(&[0; 12]);
-        buf.put_i32_le(0);
-        buf.put_i32_le(0);
+    pub fn ban(&mut self, player_name: &str, expires_at: u64) {
+        self.bans.insert(player_name.to_lowercase(), expires_at);
+    }
 
+    pub fn unban(&mut self, player_name: &str) -> bool {
+        self.bans.remove(&player_name.to_lowercase()).is_some()
+    }
 
-        buf.put_u16_le(41);
-        buf.put_u16_le(0);
-        buf.put_u32_le(0);
-        buf.put_u32_le(0);
+    pub fn ban_expiry(&self, player_name: &str, now: u64) -> Option<u64> {
+        self.bans.get(&player_name.to_lowercase())
+            .copied()
+            .filter(|&expires_at| expires_at == 0 || expires_at > now)
+    }
 
-        buf
+    pub fn prune_bans(&mut self, now: u64) {
+        self.bans.retain(|_, expires_at| *expires_at == 0 || *expires_at > now);
+    }
+
+    pub fn drops_disabled(&self) -> bool {
+        self.world_lock()
+            .and_then(|i| self.tiles[i].extra.as_ref())
+            .is_some_and(|lock| matches!(lock, TileExtra::Lock { flags, .. } if flags & locks::LOCK_NO_DROP != 0))
+    }
+
+    pub fn tile_pos(&self, index: usize) -> (u32, u32) {
+        (index as u32 % self.width, index as u32 / self.width)
+    }
+
+    pub fn tile_index(&self, x: i32, y: i32) -> Option<usize> {
+        if x < 0 || x >= self.width as i32 || y < 0 || y >= self.height as i32 {
+            return None;
+        }
+        Some((y * self.width as i32 + x) as usize)
+    }
+
+    pub fn main_door(&self) -> Option<usize> {
+        self.tiles.iter().position(|t| t.fg == 6)
+    }
+
+    pub fn find_door(&self, door_id: &str) -> Option<usize> {
+        if door_id.is_empty() { return None; }
+        self.tiles.iter().position(|t| matches!(&t.extra, Some(TileExtra::Door { door_id: id, .. }) if id.eq_ignore_ascii_case(door_id)))
     }
 
     pub fn handle_tile_change(&mut self, packet: &mut crate::network::packet::GamePacket, player_name: &str) -> TileChangeResult {
@@ -222,30 +325,39 @@ impl World {
         }
 
         let i = (y * self.width as i32 + x) as usize;
+        let item_held = packet.id;
 
-
-        if self.owner_name.is_empty() {
-             self.owner_name = player_name.to_string();
-             info!("World {} now owned by {}", self.name, self.owner_name);
+        if locks::lock_kind(self.tiles[i].fg).is_some() {
+            if item_held != 18 { return TileChangeResult::NoChange; }
+            if !self.tiles[i].extra.as_ref().is_some_and(|l| locks::is_owner(l, player_name)) {
+                return TileChangeResult::Locked;
+            }
+        } else if !self.can_build(i, player_name) {
+            return TileChangeResult::Locked;
         }
 
-
-        if !self.owner_name.is_empty() && self.owner_name != player_name {
-            return TileChangeResult::NoChange;
+        if let Some(kind) = locks::lock_kind(item_held as u16) {
+            return self.place_lock(i, item_held as u16, kind, player_name);
         }
 
         let tile = &mut self.tiles[i];
-        let item_held = packet.id;
 
         if item_held == 18 {
-
-                return self.damage_tile(x, y);
-
+            if let Some(TileExtra::Seed { planted_at, fruit_count }) = tile.extra {
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+                let grow_time = crate::database::item_config::get_item_config(tile.fg as i32).grow_time;
+                if crate::game::farming::is_ripe(planted_at, grow_time, now) {
+                    let seed_id = tile.fg;
+                    tile.fg = 0;
+                    tile.hits = 0;
+                    tile.extra = None;
+                    return TileChangeResult::Harvested(seed_id, fruit_count);
+                }
+            }
+            return self.damage_tile(x, y);
         } else if item_held == 32 {
-
             return TileChangeResult::NoChange;
         } else {
-
             let config = crate::database::item_config::get_item_config(item_held);
             if config.is_background {
                 if tile.bg == 0 {
@@ -258,9 +370,13 @@ impl World {
                     tile.fg = item_held as u16;
                     tile.hits = 0;
 
-                    if tile.fg == 12 || tile.fg == 6 {
-                        tile.label = "EXIT".to_string();
-                    }
+                    tile.extra = if config.action_type == crate::game::farming::SEED_ACTION_TYPE {
+                        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+                        tile.last_tick = now;
+                        Some(crate::game::farming::plant(now))
+                    } else {
+                        TileExtra::for_action_type(config.action_type)
+                    };
                     return TileChangeResult::PlacedFG(tile.fg);
                 }
             }
@@ -269,14 +385,46 @@ impl World {
         TileChangeResult::NoChange
     }
 
+    fn place_lock(&mut self, index: usize, item_id: u16, kind: LockKind, player_name: &str) -> TileChangeResult {
+        if self.tiles[index].fg != 0 { return TileChangeResult::NoChange; }
+
+        match kind {
+            LockKind::World => {
+                if self.world_lock().is_some() { return TileChangeResult::NoChange; }
+                if self.lock_areas.keys().any(|&l| {
+                    !self.tiles[l as usize].extra.as_ref().is_some_and(|e| locks::is_owner(e, player_name))
+                }) {
+                    return TileChangeResult::Locked;
+                }
+                self.owner_name = player_name.to_string();
+                info!("World {} now owned by {}", self.name, self.owner_name);
+            }
+            LockKind::Area(size) => {
+                if self.area_lock_at(index).is_some() { return TileChangeResult::Locked; }
+                let area = locks::flood_fill(self, index, size);
+                self.lock_areas.insert(index as u32, area);
+            }
+        }
+
+        let tile = &mut self.tiles[index];
+        tile.fg = item_id;
+        tile.hits = 0;
+        tile.extra = Some(TileExtra::Lock {
+            flags: 0,
+            owner_name: player_name.to_string(),
+            admins: Vec::new(),
+            minimum_level: 0,
+        });
+        TileChangeResult::PlacedFG(item_id)
+    }
+
     pub fn serialize_to_binary(&self) -> Vec<u8> {
         let mut buf = Vec::new();
 
-        buf.put_i32_le(1);
+        buf.put_i32_le(WORLD_FORMAT_VERSION);
 
 
-        buf.put_u16_le(self.name.len() as u16);
-        buf.extend_from_slice(self.name.as_bytes());
+        put_string(&mut buf, &self.name);
 
 
         buf.put_u32_le(self.width);
@@ -284,68 +432,199 @@ impl World {
 
 
         buf.put_i32_le(self.owner);
-        buf.put_u16_le(self.owner_name.len() as u16);
-        buf.extend_from_slice(self.owner_name.as_bytes());
+        put_string(&mut buf, &self.owner_name);
 
 
         buf.put_u32_le(self.tiles.len() as u32);
         for tile in &self.tiles {
             buf.put_u16_le(tile.fg);
             buf.put_u16_le(tile.bg);
-            buf.put_u8(tile.hits);
+            buf.put_u8(0);
             buf.put_u8(tile.state3);
             buf.put_u8(tile.state4);
             buf.put_u64_le(tile.last_tick);
+            match &tile.extra {
+                Some(extra) => extra.write(&mut buf),
+                None => buf.put_u8(0),
+            }
+        }
 
-            buf.put_u16_le(tile.label.len() as u16);
-            if !tile.label.is_empty() {
-                buf.extend_from_slice(tile.label.as_bytes());
+
+        if !self.npcs.is_empty() {
+            let mut section = Vec::new();
+            section.put_u32_le(self.npcs.len() as u32);
+            for npc in &self.npcs {
+                section.put_u32_le(npc.net_id);
+                put_string(&mut section, &npc.name);
+                section.put_f32_le(npc.x);
+                section.put_f32_le(npc.y);
+                section.put_i32_le(npc.health);
+                section.put_i32_le(npc.max_health);
+                section.put_f32_le(npc.target_x);
+                section.put_u32_le(npc.state);
             }
+            put_section(&mut buf, SECTION_NPCS, &section);
         }
 
+        if !self.lock_areas.is_empty() {
+            let mut section = Vec::new();
+            section.put_u32_le(self.lock_areas.len() as u32);
+            for (lock, area) in &self.lock_areas {
+                section.put_u32_le(*lock);
+                section.put_u32_le(area.len() as u32);
+                for index in area {
+                    section.put_u32_le(*index);
+                }
+            }
+            put_section(&mut buf, SECTION_LOCK_AREAS, &section);
+        }
+
+        if !self.objects.is_empty() || self.last_object_uid != 0 {
+            let mut section = Vec::new();
+            section.put_u32_le(self.last_object_uid);
+            section.put_u32_le(self.objects.len() as u32);
+            for object in &self.objects {
+                section.put_u32_le(object.uid);
+                section.put_u16_le(object.item_id);
+                section.put_u8(object.count);
+                section.put_u8(object.flags);
+                section.put_f32_le(object.x);
+                section.put_f32_le(object.y);
+            }
+            put_section(&mut buf, SECTION_OBJECTS, &section);
+        }
+
+        if self.settings != WorldSettings::default() {
+            let mut section = Vec::new();
+            let mut flags = 0;
+            if self.settings.no_punch { flags |= SETTING_NO_PUNCH; }
+            if self.settings.zombie { flags |= SETTING_ZOMBIE; }
+            section.put_u8(flags);
+            section.put_u16_le(self.settings.weather);
+            put_string(&mut section, &self.settings.music);
+            put_string(&mut section, &self.settings.description);
+            put_section(&mut buf, SECTION_SETTINGS, &section);
+        }
+
+        if !self.bans.is_empty() {
+            let mut section = Vec::new();
+            section.put_u32_le(self.bans.len() as u32);
+            for (name, expires_at) in &self.bans {
+                put_string(&mut section, name);
+                section.put_u64_le(*expires_at);
+            }
+            put_section(&mut buf, SECTION_BANS, &section);
+        }
+
+        buf.put_u8(SECTION_END);
         buf
     }
 
     pub fn deserialize_from_binary(data: &[u8]) -> Result<Self> {
-        let mut buf = &data[..];
-        let _version = buf.get_i32_le();
+        let mut reader = BinReader::new(data);
+        let version = reader.i32()?;
+
+        let mut world = match version {
+            1 => Self::decode_v1(&mut reader)?,
+            2 => Self::decode_v2(&mut reader)?,
+            3 => Self::decode_v3(&mut reader)?,
+            v => bail!("unsupported world format version {}", v),
+        };
 
-        let name_len = buf.get_u16_le() as usize;
-        let name = String::from_utf8(buf.copy_to_bytes(name_len).to_vec())?;
+        world.clear_stale_owner();
+        Ok(world)
+    }
 
-        let width = buf.get_u32_le();
-        let height = buf.get_u32_le();
+    // Ownership comes from the world lock tile; a saved owner without one is stale.
+    pub fn clear_stale_owner(&mut self) {
+        if self.world_lock().is_none() && !self.owner_name.is_empty() {
+            info!("Clearing unlocked owner {} of world {}", self.owner_name, self.name);
+            self.owner_name.clear();
+            self.owner = 0;
+        }
+    }
 
-        let owner = buf.get_i32_le();
-        let owner_name_len = buf.get_u16_le() as usize;
-        let owner_name = String::from_utf8(buf.copy_to_bytes(owner_name_len).to_vec())?;
+    fn decode_v1(reader: &mut BinReader<'_>) -> Result<Self> {
+        let world = Self::decode_core(reader, read_legacy_label)?;
+        info!("Migrating world {} from format v1", world.name);
+        Ok(world)
+    }
+
+    fn decode_v2(reader: &mut BinReader<'_>) -> Result<Self> {
+        let mut world = Self::decode_core(reader, read_legacy_label)?;
+        world.decode_sections(reader)?;
+        info!("Migrating world {} from format v2", world.name);
+        Ok(world)
+    }
+
+    fn decode_v3(reader: &mut BinReader<'_>) -> Result<Self> {
+        let mut world = Self::decode_core(reader, read_extra)?;
+        world.decode_sections(reader)?;
+        Ok(world)
+    }
 
-        let tile_count = buf.get_u32_le() as usize;
+    fn decode_sections(&mut self, reader: &mut BinReader<'_>) -> Result<()> {
+        loop {
+            let tag = reader.u8()?;
+            if tag == SECTION_END { break; }
+
+            let len = reader.u32()? as usize;
+            let mut section = BinReader::new(reader.bytes(len)?);
+            match tag {
+                SECTION_NPCS => self.npcs = decode_npcs(&mut section)?,
+                SECTION_LOCK_AREAS => self.lock_areas = decode_lock_areas(&mut section, self.tiles.len())?,
+                SECTION_OBJECTS => (self.last_object_uid, self.objects) = decode_objects(&mut section)?,
+                SECTION_SETTINGS => self.settings = decode_settings(&mut section)?,
+                SECTION_BANS => self.bans = decode_bans(&mut section)?,
+                _ => warn!("Skipping unknown section {} in world {}", tag, self.name),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn decode_core(
+        reader: &mut BinReader<'_>,
+        read_tile_extra: fn(&mut BinReader<'_>) -> Result<Option<TileExtra>>,
+    ) -> Result<Self> {
+        let name = reader.string()?;
+
+        let width = reader.u32()?;
+        let height = reader.u32()?;
+
+        let owner = reader.i32()?;
+        let owner_name = reader.string()?;
+
+        let tile_count = reader.u32()? as usize;
+        if tile_count != (width as usize) * (height as usize) {
+            bail!("world {} has {} tiles, expected {}x{}", name, tile_count, width, height);
+        }
         let mut tiles = Vec::with_capacity(tile_count);
 
         for _ in 0..tile_count {
-            let fg = buf.get_u16_le();
-            let bg = buf.get_u16_le();
-            let hits = buf.get_u8();
-            let state3 = buf.get_u8();
-            let state4 = buf.get_u8();
-            let last_tick = buf.get_u64_le();
-
-            let label_len = buf.get_u16_le() as usize;
-            let label = if label_len > 0 {
-                String::from_utf8(buf.copy_to_bytes(label_len).to_vec())?
-            } else {
-                String::new()
-            };
+            let fg = reader.u16()?;
+            let bg = reader.u16()?;
+            let _hits = reader.u8()?;
+            let state3 = reader.u8()?;
+            let state4 = reader.u8()?;
+            let last_tick = reader.u64()?;
+            let extra = read_tile_extra(reader)?;
 
             tiles.push(Tile {
-                fg, bg, state3, state4, hits, label, last_tick
+                fg, bg, state3, state4, hits: 0, extra, last_tick
             });
         }
 
         Ok(Self {
             name, width, height, owner_name, owner, tiles,
             npcs: Vec::new(),
+            lock_areas: BTreeMap::new(),
+            objects: Vec::new(),
+            last_object_uid: 0,
+            visits: 0,
+            settings: WorldSettings::default(),
+            bans: BTreeMap::new(),
+            damage_times: HashMap::new(),
         })
     }
 
@@ -354,6 +633,36 @@ impl World {
             return TileChangeResult::NoChange;
         }
         let i = (y * self.width as i32 + x) as usize;
+        let result = self.apply_damage(i, x, y);
+
+        match result {
+            TileChangeResult::Damaged(..) => {
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+                self.damage_times.insert(i as u32, now);
+            }
+            TileChangeResult::BrokeFG(..) | TileChangeResult::BrokeBG(..) => { self.damage_times.remove(&(i as u32)); }
+            _ => {}
+        }
+        result
+    }
+
+    pub fn heal_damage(&mut self, now_ms: u64, default_delay_ms: u64) -> Vec<usize> {
+        let mut healed = Vec::new();
+        self.damage_times.retain(|&index, &mut hit_at| {
+            let Some(tile) = self.tiles.get_mut(index as usize) else { return false; };
+            let item_id = if tile.fg != 0 { tile.fg } else { tile.bg };
+            let restore_time = crate::database::item_config::get_item_config(item_id as i32).restore_time;
+            let delay = if restore_time > 0 { restore_time as u64 * 1000 } else { default_delay_ms };
+
+            if now_ms.saturating_sub(hit_at) < delay { return true; }
+            tile.hits = 0;
+            healed.push(index as usize);
+            false
+        });
+        healed
+    }
+
+    fn apply_damage(&mut self, i: usize, x: i32, y: i32) -> TileChangeResult {
         let tile = &mut self.tiles[i];
 
         if tile.fg != 0 {
@@ -371,7 +680,17 @@ impl World {
                 let id_before = tile.fg;
                 tile.fg = 0;
                 tile.hits = 0;
-                tile.label = String::new();
+                tile.extra = None;
+
+                match locks::lock_kind(id_before) {
+                    Some(LockKind::World) => {
+                        self.owner_name.clear();
+                        self.owner = 0;
+                        self.settings = WorldSettings::default();
+                    }
+                    Some(LockKind::Area(_)) => { self.lock_areas.remove(&(i as u32)); }
+                    None => {}
+                }
 
                 return TileChangeResult::BrokeFG(id_before, current_hits);
             }
@@ -390,7 +709,7 @@ impl World {
                 let id_before = tile.bg;
                 tile.bg = 0;
                 tile.hits = 0;
-                tile.label = String::new();
+                tile.extra = None;
 
                 return TileChangeResult::BrokeBG(id_before, current_hits);
             }
@@ -401,6 +720,197 @@ impl World {
     }
 }
 
+fn write_client_tile(buf: &mut Vec<u8>, tile: &Tile, now: u64) {
+    let mut flags = tile.state3 as u16 | ((tile.state4 as u16) << 8);
+
+
+    let extra = match &tile.extra {
+        Some(extra) => Some(extra.clone()),
+        None => {
+            let config = crate::database::item_config::get_item_config(tile.fg as i32);
+            TileExtra::for_action_type(config.action_type)
+        }
+    };
+
+    if extra.is_some() {
+        flags |= 0x0001;
+    }
+
+    buf.put_u16_le(tile.fg);
+    buf.put_u16_le(tile.bg);
+    buf.put_u16_le(0);
+    buf.put_u16_le(flags);
+
+    if let Some(extra) = extra {
+        extra.write_client(buf, now);
+    }
+}
+
+fn read_legacy_label(reader: &mut BinReader<'_>) -> Result<Option<TileExtra>> {
+    let label = reader.string()?;
+    if label.is_empty() { return Ok(None); }
+    Ok(Some(TileExtra::Door { label, destination: String::new(), door_id: String::new(), locked: false }))
+}
+
+fn read_extra(reader: &mut BinReader<'_>) -> Result<Option<TileExtra>> {
+    match reader.peek_u8()? {
+        0 => { reader.u8()?; Ok(None) }
+        _ => Ok(Some(TileExtra::read(reader)?)),
+    }
+}
+
+pub fn put_string(buf: &mut Vec<u8>, value: &str) {
+    buf.put_u16_le(value.len() as u16);
+    buf.extend_from_slice(value.as_bytes());
+}
+
+fn put_section(buf: &mut Vec<u8>, tag: u8, section: &[u8]) {
+    buf.put_u8(tag);
+    buf.put_u32_le(section.len() as u32);
+    buf.extend_from_slice(section);
+}
+
+fn decode_npcs(reader: &mut BinReader<'_>) -> Result<Vec<crate::game::npc::Npc>> {
+    let count = reader.u32()? as usize;
+    let mut npcs = Vec::with_capacity(count.min(256));
+    for _ in 0..count {
+        let net_id = reader.u32()?;
+        let name = reader.string()?;
+        let x = reader.f32()?;
+        let y = reader.f32()?;
+        let health = reader.i32()?;
+        let mut npc = crate::game::npc::Npc::new(net_id, name, x, y, health);
+        npc.max_health = reader.i32()?;
+        npc.target_x = reader.f32()?;
+        npc.state = reader.u32()?;
+        npcs.push(npc);
+    }
+    Ok(npcs)
+}
+
+fn decode_lock_areas(reader: &mut BinReader<'_>, tile_count: usize) -> Result<BTreeMap<u32, Vec<u32>>> {
+    let count = reader.u32()? as usize;
+    let mut areas = BTreeMap::new();
+    for _ in 0..count {
+        let lock = reader.u32()?;
+        let len = reader.u32()? as usize;
+        let mut area = Vec::with_capacity(len.min(256));
+        for _ in 0..len {
+            let index = reader.u32()?;
+            if index as usize >= tile_count {
+                bail!("lock area tile {} out of bounds", index);
+            }
+            area.push(index);
+        }
+        area.sort_unstable();
+        areas.insert(lock, area);
+    }
+    Ok(areas)
+}
+
+fn decode_objects(reader: &mut BinReader<'_>) -> Result<(u32, Vec<WorldObject>)> {
+    let last_uid = reader.u32()?;
+    let count = reader.u32()? as usize;
+    let mut objects = Vec::with_capacity(count.min(MAX_OBJECTS));
+    for _ in 0..count {
+        objects.push(WorldObject {
+            uid: reader.u32()?,
+            item_id: reader.u16()?,
+            count: reader.u8()?,
+            flags: reader.u8()?,
+            x: reader.f32()?,
+            y: reader.f32()?,
+        });
+    }
+    Ok((last_uid, objects))
+}
+
+fn decode_settings(reader: &mut BinReader<'_>) -> Result<WorldSettings> {
+    let flags = reader.u8()?;
+    Ok(WorldSettings {
+        no_punch: flags & SETTING_NO_PUNCH != 0,
+        zombie: flags & SETTING_ZOMBIE != 0,
+        weather: reader.u16()?,
+        music: reader.string()?,
+        description: reader.string()?,
+    })
+}
+
+fn decode_bans(reader: &mut BinReader<'_>) -> Result<BTreeMap<String, u64>> {
+    let count = reader.u32()?;
+    let mut bans = BTreeMap::new();
+    for _ in 0..count {
+        let name = reader.string()?;
+        bans.insert(name, reader.u64()?);
+    }
+    Ok(bans)
+}
+
+pub struct BinReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> BinReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn peek_u8(&self) -> Result<u8> {
+        match self.data.first() {
+            Some(b) => Ok(*b),
+            None => bail!("world data truncated: wanted 1 byte, 0 left"),
+        }
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.data.len() < len {
+            bail!("world data truncated: wanted {} bytes, {} left", len, self.data.len());
+        }
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut out = [0u8; N];
+        out.copy_from_slice(self.bytes(N)?);
+        Ok(out)
+    }
+
+    pub fn u8(&mut self) -> Result<u8> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    pub fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    pub fn i32(&mut self) -> Result<i32> {
+        Ok(i32::from_le_bytes(self.array()?))
+    }
+
+    pub fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    pub fn f32(&mut self) -> Result<f32> {
+        Ok(f32::from_le_bytes(self.array()?))
+    }
+
+    pub fn string(&mut self) -> Result<String> {
+        let len = self.u16()? as usize;
+        Ok(String::from_utf8(self.bytes(len)?.to_vec())?)
+    }
+}
+
 pub fn init_db() -> Result<(), rusqlite::Error> {
     let conn = rusqlite::Connection::open("db/worlds.db")?;
     conn.execute(
@@ -412,6 +922,8 @@ pub fn init_db() -> Result<(), rusqlite::Error> {
         )",
         [],
     )?;
+    let _ = conn.execute("ALTER TABLE worlds ADD COLUMN visits INTEGER DEFAULT 0", []);
+    conn.execute("CREATE TABLE IF NOT EXISTS banned_worlds (name TEXT PRIMARY KEY)", [])?;
     Ok(())
 }
 
@@ -431,15 +943,15 @@ pub fn save_world_internal(tx: &rusqlite::Transaction, world: &World) -> Result<
     let compressed_data = zstd::encode_all(&bin_data[..], 3)?;
 
     tx.execute(
-        "INSERT OR REPLACE INTO worlds (name, owner_name, owner, data) VALUES (?1, ?2, ?3, ?4)",
-        rusqlite::params![world.name, world.owner_name, world.owner, compressed_data],
+        "INSERT OR REPLACE INTO worlds (name, owner_name, owner, data, visits) VALUES (?1, ?2, ?3, ?4, ?5)",
+        rusqlite::params![world.name, world.owner_name, world.owner, compressed_data, world.visits],
     )?;
     Ok(())
 }
 
 pub fn load_world(name: &str) -> Result<Option<World>> {
     let conn = rusqlite::Connection::open("db/worlds.db")?;
-    let mut stmt = conn.prepare("SELECT data FROM worlds WHERE name = ?1")?;
+    let mut stmt = conn.prepare("SELECT data, visits FROM worlds WHERE name = ?1")?;
     let mut rows = stmt.query(rusqlite::params![name.to_uppercase()])?;
 
     if let Some(row) = rows.next()? {
@@ -449,9 +961,250 @@ pub fn load_world(name: &str) -> Result<Option<World>> {
         let bin_data = zstd::decode_all(&compressed_data[..])?;
 
 
-        let world = World::deserialize_from_binary(&bin_data)?;
+        let mut world = World::deserialize_from_binary(&bin_data)?;
+        world.visits = row.get::<_, Option<u32>>(1)?.unwrap_or(0);
         Ok(Some(world))
     } else {
         Ok(None)
     }
-}
\ No newline at end of file
+}
+
+pub fn delete_world_internal(tx: &rusqlite::Transaction, name: &str) -> Result<()> {
+    tx.execute("DELETE FROM worlds WHERE name = ?1", rusqlite::params![name])?;
+    Ok(())
+}
+
+pub fn is_world_banned(name: &str) -> Result<bool> {
+    let conn = rusqlite::Connection::open("db/worlds.db")?;
+    let mut stmt = conn.prepare("SELECT 1 FROM banned_worlds WHERE name = ?1 LIMIT 1")?;
+    Ok(stmt.exists(rusqlite::params![name])?)
+}
+
+pub fn set_world_banned(name: &str, banned: bool) -> Result<()> {
+    let conn = rusqlite::Connection::open("db/worlds.db")?;
+    if banned {
+        conn.execute("INSERT OR IGNORE INTO banned_worlds (name) VALUES (?1)", rusqlite::params![name])?;
+    } else {
+        conn.execute("DELETE FROM banned_worlds WHERE name = ?1", rusqlite::params![name])?;
+    }
+    Ok(())
+}
+
+pub fn worlds_owned_by(owner_name: &str) -> Result<Vec<String>> {
+    let conn = rusqlite::Connection::open("db/worlds.db")?;
+    let mut stmt = conn.prepare("SELECT name FROM worlds WHERE owner_name = ?1 COLLATE NOCASE ORDER BY name LIMIT 50")?;
+    let names = stmt.query_map(rusqlite::params![owner_name], |r| r.get(0))?
+        .collect::<Result<Vec<String>, _>>()?;
+    Ok(names)
+}
+
+pub fn most_visited_worlds(limit: usize) -> Result<Vec<(String, u32)>> {
+    let conn = rusqlite::Connection::open("db/worlds.db")?;
+    let mut stmt = conn.prepare("SELECT name, visits FROM worlds WHERE visits > 0 ORDER BY visits DESC, name LIMIT ?1")?;
+    let worlds = stmt.query_map(rusqlite::params![limit as i64], |r| Ok((r.get(0)?, r.get(1)?)))?
+        .collect::<Result<Vec<(String, u32)>, _>>()?;
+    Ok(worlds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::npc::Npc;
+
+    fn small_world() -> World {
+        World {
+            name: "TEST".to_string(),
+            width: 3,
+            height: 2,
+            owner_name: "owner".to_string(),
+            owner: 7,
+            tiles: vec![Tile::new(2, 14); 6],
+            npcs: Vec::new(),
+            lock_areas: BTreeMap::new(),
+            objects: Vec::new(),
+            last_object_uid: 0,
+            visits: 0,
+            settings: WorldSettings::default(),
+            bans: BTreeMap::new(),
+            damage_times: HashMap::new(),
+        }
+    }
+
+    fn lock(owner: &str, admins: Vec<&str>, flags: u8) -> TileExtra {
+        TileExtra::Lock {
+            flags,
+            owner_name: owner.to_string(),
+            admins: admins.into_iter().map(str::to_string).collect(),
+            minimum_level: 0,
+        }
+    }
+
+    fn encode_v1(world: &World) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.put_i32_le(1);
+        put_string(&mut buf, &world.name);
+        buf.put_u32_le(world.width);
+        buf.put_u32_le(world.height);
+        buf.put_i32_le(world.owner);
+        put_string(&mut buf, &world.owner_name);
+        buf.put_u32_le(world.tiles.len() as u32);
+        for tile in &world.tiles {
+            buf.put_u16_le(tile.fg);
+            buf.put_u16_le(tile.bg);
+            buf.put_u8(tile.hits);
+            buf.put_u8(tile.state3);
+            buf.put_u8(tile.state4);
+            buf.put_u64_le(tile.last_tick);
+            put_string(&mut buf, if tile.extra.is_some() { "EXIT" } else { "" });
+        }
+        buf
+    }
+
+    #[test]
+    fn round_trip_keeps_npcs() {
+        let mut world = small_world();
+        world.tiles[1].extra = Some(TileExtra::Sign { text: "hi".to_string() });
+        let mut npc = Npc::new(1000, "Boss".to_string(), 48.0, 16.0, 100);
+        npc.health = 40;
+        world.npcs.push(npc);
+
+        world.tiles[0] = Tile::new(242, 14);
+        world.tiles[0].extra = Some(lock("owner", Vec::new(), 0));
+        world.lock_areas.insert(5, vec![4, 5]);
+        world.spawn_object(2, 3, 40.0, 8.5);
+        world.spawn_object(112, 5, 16.0, 0.0);
+        world.objects.remove(0);
+        world.ban("Griefer", 0);
+        world.settings = WorldSettings { no_punch: true, zombie: true, music: "theme2".to_string(), weather: 5, description: "hello".to_string() };
+
+        let decoded = World::deserialize_from_binary(&world.serialize_to_binary()).unwrap();
+        assert_eq!(decoded.name, "TEST");
+        assert_eq!(decoded.owner_name, "owner");
+        assert_eq!(decoded.lock_areas, world.lock_areas);
+        assert_eq!(decoded.objects, world.objects);
+        assert_eq!(decoded.last_object_uid, 2);
+        assert_eq!(decoded.settings, world.settings);
+        assert_eq!(decoded.bans, world.bans);
+        assert_eq!(decoded.tiles.len(), 6);
+        assert_eq!(decoded.tiles[1].extra, Some(TileExtra::Sign { text: "hi".to_string() }));
+        assert_eq!(decoded.npcs.len(), 1);
+        assert_eq!(decoded.npcs[0].net_id, 1000);
+        assert_eq!(decoded.npcs[0].health, 40);
+        assert_eq!(decoded.npcs[0].max_health, 100);
+    }
+
+    #[test]
+    fn migrates_v1_blobs() {
+        let mut world = small_world();
+        world.tiles[2].extra = Some(TileExtra::Sign { text: String::new() });
+        let decoded = World::deserialize_from_binary(&encode_v1(&world)).unwrap();
+        assert_eq!(decoded.name, world.name);
+        assert_eq!(decoded.tiles.len(), 6);
+        assert!(decoded.npcs.is_empty());
+        assert!(matches!(&decoded.tiles[2].extra, Some(TileExtra::Door { label, .. }) if label == "EXIT"));
+        assert_eq!(decoded.tiles[0].extra, None);
+    }
+
+    #[test]
+    fn truncated_data_is_an_error() {
+        let mut world = small_world();
+        world.npcs.push(Npc::new(1000, "Boss".to_string(), 0.0, 0.0, 10));
+        let data = world.serialize_to_binary();
+        for len in 0..data.len() {
+            assert!(World::deserialize_from_binary(&data[..len]).is_err(), "len {}", len);
+        }
+    }
+
+    #[test]
+    fn unknown_sections_are_skipped() {
+        let mut data = small_world().serialize_to_binary();
+        data.pop();
+        put_section(&mut data, 200, &[1, 2, 3]);
+        data.put_u8(SECTION_END);
+        assert!(World::deserialize_from_binary(&data).is_ok());
+    }
+
+    #[test]
+    fn rejects_unknown_versions() {
+        let mut data = small_world().serialize_to_binary();
+        data[..4].copy_from_slice(&99i32.to_le_bytes());
+        assert!(World::deserialize_from_binary(&data).is_err());
+    }
+
+    #[test]
+    fn unlocked_owners_are_cleared_on_load() {
+        let decoded = World::deserialize_from_binary(&small_world().serialize_to_binary()).unwrap();
+        assert!(decoded.owner_name.is_empty());
+        assert_eq!(decoded.owner, 0);
+    }
+
+    #[test]
+    fn locks_gate_building() {
+        let mut world = small_world();
+        assert!(world.can_build(3, "anyone"));
+
+        world.tiles[0] = Tile::new(242, 0);
+        world.tiles[0].extra = Some(lock("owner", vec!["Helper"], 0));
+        assert!(world.can_build(3, "OWNER"));
+        assert!(world.can_build(3, "helper"));
+        assert!(!world.can_build(3, "stranger"));
+        assert_eq!(world.lock_owner(3), Some("owner"));
+
+        world.tiles[5] = Tile::new(202, 0);
+        world.tiles[5].extra = Some(lock("stranger", Vec::new(), 0));
+        world.lock_areas.insert(5, vec![2, 5]);
+        assert!(world.can_build(2, "stranger"));
+        assert!(!world.can_build(2, "owner"));
+        assert!(!world.can_build(3, "stranger"));
+
+        world.tiles[0].extra = Some(lock("owner", Vec::new(), locks::LOCK_PUBLIC | locks::LOCK_NO_DROP));
+        assert!(world.can_build(3, "stranger"));
+        assert!(world.drops_disabled());
+    }
+
+    #[test]
+    fn area_flood_fill_stops_at_other_locks() {
+        let mut world = small_world();
+        world.tiles[1] = Tile::new(202, 0);
+        world.lock_areas.insert(1, vec![1, 2]);
+        world.tiles[4].fg = 6;
+
+        let area = locks::flood_fill(&world, 0, 10);
+        assert_eq!(area, vec![0, 3]);
+        assert_eq!(locks::flood_fill(&world, 0, 1), vec![0]);
+
+        world.tiles[4].fg = 0;
+        assert_eq!(locks::flood_fill(&world, 0, 10), vec![0, 3, 4, 5]);
+    }
+
+    #[test]
+    fn world_bans_expire() {
+        let mut world = small_world();
+        world.ban("Griefer", 100);
+        world.ban("forever", 0);
+        assert_eq!(world.ban_expiry("GRIEFER", 99), Some(100));
+        assert_eq!(world.ban_expiry("griefer", 100), None);
+        assert_eq!(world.ban_expiry("Forever", u64::MAX), Some(0));
+
+        world.prune_bans(100);
+        assert_eq!(world.bans.len(), 1);
+        assert!(world.unban("FOREVER"));
+        assert!(!world.unban("forever"));
+    }
+
+    #[test]
+    fn damage_heals_after_delay() {
+        let mut world = small_world();
+        world.tiles[1].hits = 2;
+        world.tiles[4].hits = 1;
+        world.damage_times.insert(1, 1_000);
+        world.damage_times.insert(4, 5_000);
+
+        assert!(world.heal_damage(8_999, 8_000).is_empty());
+        assert_eq!(world.heal_damage(9_000, 8_000), vec![1]);
+        assert_eq!(world.tiles[1].hits, 0);
+        assert_eq!(world.tiles[4].hits, 1);
+        assert_eq!(world.heal_damage(20_000, 8_000), vec![4]);
+        assert!(world.damage_times.is_empty());
+    }
+}
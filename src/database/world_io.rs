@@ -0,0 +1,273 @@
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, bail, Context, Result};
+use crate::database::schematic::{Schematic, SCHEMATIC_EXTENSION};
+use crate::database::tile_extra::TileExtra;
+use crate::database::world::{Tile, World, MAX_OBJECTS};
+use crate::game::locks::lock_kind;
+use crate::game::worldgen::{MAX_HEIGHT, MAX_WIDTH, MIN_HEIGHT, MIN_WIDTH};
+
+pub const WORLD_FILE_DIR: &str = "worlds";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WorldFormat {
+    Json,
+    Schematic,
+}
+
+impl WorldFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "json" => Some(WorldFormat::Json),
+            "schem" | "schematic" | SCHEMATIC_EXTENSION => Some(WorldFormat::Schematic),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        Self::parse(path.extension()?.to_str()?)
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            WorldFormat::Json => "json",
+            WorldFormat::Schematic => SCHEMATIC_EXTENSION,
+        }
+    }
+}
+
+pub fn world_file_path(file_name: &str) -> Option<PathBuf> {
+    let (stem, extension) = file_name.rsplit_once('.')?;
+    WorldFormat::parse(extension)?;
+    crate::database::schematic::is_valid_file_name(stem)
+        .then(|| PathBuf::from(WORLD_FILE_DIR).join(format!("{}.{}", stem.to_lowercase(), extension.to_lowercase())))
+}
+
+pub fn encode(world: &World, format: WorldFormat) -> Result<Vec<u8>> {
+    match format {
+        WorldFormat::Json => Ok(serde_json::to_vec_pretty(world)?),
+        WorldFormat::Schematic => {
+            if let Some(index) = world.tiles.iter().position(|t| lock_kind(t.fg).is_some()) {
+                let (x, y) = world.tile_pos(index);
+                bail!("lock at {}, {} can't be stored in a schematic, export as json instead", x, y);
+            }
+            Schematic {
+                width: world.width,
+                height: world.height,
+                tiles: world.tiles.iter().map(|t| (t.fg, t.bg)).collect(),
+            }.to_bytes()
+        }
+    }
+}
+
+pub fn decode(data: &[u8], format: WorldFormat, name: &str) -> Result<World> {
+    let mut world = match format {
+        WorldFormat::Json => {
+            let mut world = serde_json::from_slice::<World>(data)?;
+            world.clear_stale_owner();
+            world
+        }
+        WorldFormat::Schematic => {
+            let schematic = Schematic::from_bytes(data)?;
+            let mut world = World::blank(name, schematic.width, schematic.height);
+            for (index, &(fg, bg)) in schematic.tiles.iter().enumerate() {
+                if lock_kind(fg).is_some() {
+                    let (x, y) = world.tile_pos(index);
+                    bail!("lock at {}, {} can't be imported from a schematic", x, y);
+                }
+                if let Some(id) = [fg, bg].into_iter().find(|&id| id != 0 && !known_item(id)) {
+                    let (x, y) = world.tile_pos(index);
+                    bail!("unknown item {} at {}, {}", id, x, y);
+                }
+                world.restore_tile(index, (fg, bg));
+            }
+            world
+        }
+    };
+    world.name = name.to_uppercase();
+    world.visits = 0;
+    Ok(world)
+}
+
+pub fn known_item(id: u16) -> bool {
+    crate::database::item_config::ITEMS.contains_key(&(id as i32))
+}
+
+pub fn validate(world: &World, known_item: impl Fn(u16) -> bool) -> Result<()> {
+    if !(MIN_WIDTH..=MAX_WIDTH).contains(&world.width) || !(MIN_HEIGHT..=MAX_HEIGHT).contains(&world.height) {
+        bail!("world size {}x{} must be between {}x{} and {}x{}", world.width, world.height, MIN_WIDTH, MIN_HEIGHT, MAX_WIDTH, MAX_HEIGHT);
+    }
+    let expected = (world.width * world.height) as usize;
+    if world.tiles.len() != expected {
+        bail!("world has {} tiles, expected {}", world.tiles.len(), expected);
+    }
+
+    for (index, tile) in world.tiles.iter().enumerate() {
+        if let Some(id) = [tile.fg, tile.bg].into_iter().find(|&id| id != 0 && !known_item(id)) {
+            let (x, y) = world.tile_pos(index);
+            bail!("unknown item {} at {}, {}", id, x, y);
+        }
+        if !extra_matches(tile) {
+            let (x, y) = world.tile_pos(index);
+            bail!("tile data at {}, {} doesn't match item {}", x, y, tile.fg);
+        }
+    }
+
+    if world.objects.len() > MAX_OBJECTS {
+        bail!("world has {} dropped objects, at most {} are allowed", world.objects.len(), MAX_OBJECTS);
+    }
+    if let Some(object) = world.objects.iter().find(|o| !known_item(o.item_id)) {
+        bail!("unknown dropped item {}", object.item_id);
+    }
+    if world.lock_areas.iter().any(|(&lock, area)| lock as usize >= expected || area.iter().any(|&i| i as usize >= expected)) {
+        bail!("lock area points outside the world");
+    }
+    Ok(())
+}
+
+fn extra_matches(tile: &Tile) -> bool {
+    let Some(extra) = &tile.extra else { return true; };
+    match (extra, lock_kind(tile.fg)) {
+        (TileExtra::Lock { .. }, lock) => lock.is_some(),
+        (_, Some(_)) => false,
+        _ => {
+            let config = crate::database::item_config::get_item_config(tile.fg as i32);
+            TileExtra::for_action_type(config.action_type).is_some_and(|expected| expected.type_id() == extra.type_id())
+        }
+    }
+}
+
+pub fn export_to_file(world: &World, path: &Path) -> Result<()> {
+    let format = WorldFormat::from_path(path).ok_or_else(|| anyhow!("{} must end in .json or .{}", path.display(), SCHEMATIC_EXTENSION))?;
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, encode(world, format)?).with_context(|| format!("writing {}", path.display()))?;
+    Ok(())
+}
+
+pub fn import_from_file(path: &Path, name: &str) -> Result<World> {
+    let format = WorldFormat::from_path(path).ok_or_else(|| anyhow!("{} must end in .json or .{}", path.display(), SCHEMATIC_EXTENSION))?;
+    let data = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    let world = decode(&data, format, name)?;
+    validate(&world, known_item)?;
+    Ok(world)
+}
+
+pub fn run_cli(args: &[String]) -> Option<Result<String>> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        ["export-world", world_name, path] => Some(export_world_cli(world_name, Path::new(path))),
+        ["import-world", path, world_name] => Some(import_world_cli(Path::new(path), world_name)),
+        ["export-world" | "import-world", ..] => Some(Err(anyhow!(
+            "usage: export-world <world> <file.json|file.{0}> or import-world <file.json|file.{0}> <world>", SCHEMATIC_EXTENSION
+        ))),
+        _ => None,
+    }
+}
+
+fn export_world_cli(world_name: &str, path: &Path) -> Result<String> {
+    let world_name = crate::game::world_names::normalize(world_name).map_err(|e| anyhow!("invalid world name: {:?}", e))?;
+    let world = crate::database::world::load_world(&world_name)?.ok_or_else(|| anyhow!("world {} does not exist", world_name))?;
+    export_to_file(&world, path)?;
+    Ok(format!("Exported {} to {}", world_name, path.display()))
+}
+
+fn import_world_cli(path: &Path, world_name: &str) -> Result<String> {
+    let world_name = crate::game::world_names::validate(world_name).map_err(|e| anyhow!("invalid world name: {:?}", e))?;
+    if crate::database::world::load_world(&world_name)?.is_some() {
+        bail!("world {} already exists", world_name);
+    }
+    let world = import_from_file(path, &world_name)?;
+    crate::database::world::save_world(&world)?;
+    Ok(format!("Imported {} from {} ({}x{})", world_name, path.display(), world.width, world.height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world() -> World {
+        let mut world = World::blank("SOURCE", MIN_WIDTH, MIN_HEIGHT);
+        for tile in world.tiles.iter_mut().skip(MIN_WIDTH as usize * 10) {
+            tile.fg = 2;
+            tile.bg = 14;
+        }
+        world.visits = 7;
+        world.settings.description = "Tutorial".to_string();
+        world
+    }
+
+    #[test]
+    fn json_round_trips_under_new_name() {
+        let source = world();
+        let bytes = encode(&source, WorldFormat::Json).unwrap();
+        let imported = decode(&bytes, WorldFormat::Json, "copy").unwrap();
+        assert_eq!(imported.name, "COPY");
+        assert_eq!(imported.visits, 0);
+        assert_eq!(imported.settings.description, "Tutorial");
+        assert_eq!(imported.serialize_to_binary(), World { name: "COPY".to_string(), visits: 0, ..source }.serialize_to_binary());
+    }
+
+    #[test]
+    fn schematics_reject_lock_tiles() {
+        let mut source = world();
+        source.tiles[3].fg = 242;
+        assert!(encode(&source, WorldFormat::Schematic).is_err());
+
+        let schematic = Schematic {
+            width: source.width,
+            height: source.height,
+            tiles: source.tiles.iter().map(|t| (t.fg, t.bg)).collect(),
+        };
+        let error = decode(&schematic.to_bytes().unwrap(), WorldFormat::Schematic, "copy").unwrap_err();
+        assert!(error.to_string().contains("lock"));
+    }
+
+    #[test]
+    fn validation_checks_items_and_size() {
+        let mut world = world();
+        assert!(validate(&world, |id| id == 2 || id == 14).is_ok());
+        assert!(validate(&world, |id| id == 2).is_err());
+
+        world.tiles.pop();
+        assert!(validate(&world, |_| true).is_err());
+
+        let small = World::blank("SMALL", MIN_WIDTH - 1, MIN_HEIGHT);
+        assert!(validate(&small, |_| true).is_err());
+    }
+
+    #[test]
+    fn json_import_clears_owner_without_world_lock() {
+        let mut source = world();
+        source.owner_name = "owner".to_string();
+        source.owner = 5;
+        let imported = decode(&encode(&source, WorldFormat::Json).unwrap(), WorldFormat::Json, "copy").unwrap();
+        assert_eq!(imported.owner_name, "");
+        assert_eq!(imported.owner, 0);
+    }
+
+    #[test]
+    fn validation_rejects_mismatched_tile_data() {
+        let lock = TileExtra::Lock { flags: 0, owner_name: "owner".to_string(), admins: Vec::new(), minimum_level: 0 };
+        let mut world = world();
+        world.tiles[0].fg = 242;
+        world.tiles[0].extra = Some(lock.clone());
+        assert!(validate(&world, |_| true).is_ok());
+
+        world.tiles[1].extra = Some(lock);
+        assert!(validate(&world, |_| true).is_err());
+
+        world.tiles[1].extra = None;
+        world.tiles[0].extra = Some(TileExtra::Sign { text: "hi".to_string() });
+        assert!(validate(&world, |_| true).is_err());
+    }
+
+    #[test]
+    fn world_file_names_are_sanitized() {
+        assert_eq!(world_file_path("Tutorial.JSON"), Some(PathBuf::from("worlds/tutorial.json")));
+        assert_eq!(world_file_path("event.gtschem"), Some(PathBuf::from("worlds/event.gtschem")));
+        assert_eq!(world_file_path("../db/worlds.json"), None);
+        assert_eq!(world_file_path("tutorial.txt"), None);
+        assert_eq!(world_file_path("tutorial"), None);
+    }
+}
@@ -0,0 +1,151 @@
+use std::sync::mpsc::Sender;
+use crate::database::db_thread::DbCommand;
+use crate::database::tile_extra::TileExtra;
+use crate::database::world::World;
+use crate::game::gui::{DialogBuilder, Size};
+use crate::game::state::GameState;
+use crate::network::handler::{ActionContext, ActionRegistry};
+use crate::network::host::Host;
+use crate::network::packet::{GamePacket, TextPacket, VariantListBuilder};
+use crate::network::server::{broadcast_to_world, send_console_message, send_variant, ServerConfig};
+
+const MAX_LABEL_LEN: usize = 100;
+const MAX_DESTINATION_LEN: usize = 36;
+const MAX_DOOR_ID_LEN: usize = 11;
+
+pub fn register_handlers(registry: &mut ActionRegistry) {
+    registry.register_dialog("door_edit", handle_door_edit);
+}
+
+pub fn build_door_dialog(world: &World, index: usize) -> Option<String> {
+    let tile = world.tiles.get(index)?;
+    let Some(TileExtra::Door { label, destination, door_id, locked }) = &tile.extra else { return None; };
+    let (x, y) = world.tile_pos(index);
+    let item_name = crate::database::item_config::get_item_config(tile.fg as i32).name;
+
+    Some(DialogBuilder::new()
+        .default_color("`o")
+        .label_with_icon(Size::Big, &format!("`wEdit {}``", item_name), tile.fg)
+        .embed_data("tilex", x)
+        .embed_data("tiley", y)
+        .text_input("door_name", "Label", label, MAX_LABEL_LEN as u32)
+        .text_input("door_target", "Destination", destination, MAX_DESTINATION_LEN as u32)
+        .textbox("Enter a world name, `wWORLD:ID`` to target a door there, or `w:ID`` for a door in this world.")
+        .text_input("door_id", "ID", door_id, MAX_DOOR_ID_LEN as u32)
+        .checkbox("door_locked", "Only the owner can enter", *locked)
+        .end_dialog("door_edit", "Cancel", "OK")
+        .build())
+}
+
+pub fn show_door_editor(host: &mut Host, world: &World, peer_id: u32, player_name: &str, index: usize) -> bool {
+    let Some(dialog) = build_door_dialog(world, index) else { return false; };
+    if !world.can_build(index, player_name) {
+        send_console_message(host, peer_id, "`4You don't have access to edit this door.``");
+        return true;
+    }
+
+    let (d_data, d_c) = VariantListBuilder::new()
+        .add_string("OnDialogRequest").add_string(&dialog).build();
+    send_variant(host, peer_id, d_data, d_c, -1, 0);
+    true
+}
+
+fn sanitize_name(value: &str, max_len: usize, allow_colon: bool) -> String {
+    value.trim()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || (allow_colon && *c == ':'))
+        .take(max_len)
+        .collect::<String>()
+        .to_uppercase()
+}
+
+fn handle_door_edit(ctx: &mut ActionContext<'_>, text: &TextPacket) {
+    let peer_id = ctx.peer_id;
+    let (Some(x), Some(y)) = (text.get_parsed::<i32>("tilex"), text.get_parsed::<i32>("tiley")) else { return; };
+    let Some(world_name) = ctx.state.sessions.world(peer_id).map(str::to_string) else { return; };
+    let player_name = ctx.state.sessions.name(peer_id).unwrap_or_default().to_string();
+    let Some(world) = ctx.state.active_worlds.get_mut(&world_name) else { return; };
+    let Some(index) = world.tile_index(x, y) else { return; };
+
+    if !world.can_build(index, &player_name) {
+        send_console_message(ctx.host, peer_id, "`4You don't have access to edit this door.``");
+        return;
+    }
+
+    let Some(TileExtra::Door { label, destination, door_id, locked }) = &mut world.tiles[index].extra else { return; };
+    *label = text.get("door_name").unwrap_or_default().trim().chars().take(MAX_LABEL_LEN).collect();
+    *destination = sanitize_name(text.get("door_target").unwrap_or_default(), MAX_DESTINATION_LEN, true);
+    *door_id = sanitize_name(text.get("door_id").unwrap_or_default(), MAX_DOOR_ID_LEN, false);
+    *locked = text.checkbox("door_locked").unwrap_or(false);
+
+    ctx.db_tx.send(DbCommand::UpdateWorld(world.clone())).ok();
+
+    let update = GamePacket::send_tile_update(x, y).to_bytes_with_raw_data(&world.tile_to_bytes(index));
+    broadcast_to_world(ctx.host, &ctx.state.sessions, &world_name, &update, None, false);
+}
+
+pub fn enter_door(host: &mut Host, state: &mut GameState, db_tx: &Sender<DbCommand>, config: &ServerConfig, peer_id: u32, index: usize) {
+    let Some(world_name) = state.sessions.world(peer_id).map(str::to_string) else { return; };
+    let player_name = state.sessions.name(peer_id).unwrap_or_default().to_string();
+    let Some(world) = state.active_worlds.get(&world_name) else { return; };
+    let Some(TileExtra::Door { destination, locked, .. }) = world.tiles.get(index).and_then(|t| t.extra.as_ref()) else { return; };
+
+    if *locked && !world.can_build(index, &player_name) {
+        send_console_message(host, peer_id, "`4That door is locked.``");
+        return;
+    }
+    if destination.is_empty() { return; }
+
+    let (target_world, target_door) = match destination.split_once(':') {
+        Some((w, d)) => (w.to_string(), d.to_string()),
+        None => (destination.clone(), String::new()),
+    };
+
+    if target_world == "EXIT" {
+        crate::network::server::broadcast_on_remove(host, peer_id, &state.sessions);
+        state.leave_world(peer_id);
+        crate::network::server::send_world_select_menu(host, state, config, peer_id);
+        return;
+    }
+
+    if !target_world.is_empty() && target_world != world_name {
+        let door_id = (!target_door.is_empty()).then_some(target_door.as_str());
+        crate::network::actions::enter_world(host, state, db_tx, config, peer_id, &target_world, door_id);
+        return;
+    }
+
+    let Some(target) = world.find_door(&target_door).or_else(|| world.main_door()) else { return; };
+    let (tx, ty) = world.tile_pos(target);
+    let (px, py) = ((tx * 32) as f32, (ty * 32) as f32);
+    state.sessions.entry(peer_id).pos = (px, py);
+
+    let (v_data, v_c) = VariantListBuilder::new()
+        .add_string("OnSetPos").add_vec2(px, py).build();
+    let pkt = GamePacket::call_function(peer_id as i32, 0);
+    broadcast_to_world(host, &state.sessions, &world_name, &pkt.to_bytes(&v_data, v_c), None, false);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::player::Player;
+    use crate::network::handler::test_support::TestServer;
+
+    #[test]
+    fn door_edit_updates_label_target_and_lock() {
+        let mut server = TestServer::new();
+        let mut world = World::blank("START", 30, 30);
+        let index = world.tile_index(10, 20).unwrap();
+        world.tiles[index].fg = 12;
+        world.tiles[index].extra = TileExtra::for_action_type(2);
+        server.join(1, Player::new("builder"), world);
+
+        server.dispatch(1, "action|dialog_return\ndialog_name|door_edit\ntilex|10|\ntiley|20|\ndoor_name|Shop\ndoor_target|other:a1\ndoor_id|back door\ndoor_locked|1\n");
+
+        let Some(TileExtra::Door { label, destination, door_id, locked }) = &server.world("START").tiles[index].extra else { panic!("door extra missing"); };
+        assert_eq!(label, "Shop");
+        assert_eq!(destination, "OTHER:A1");
+        assert_eq!(door_id, "BACKDOOR");
+        assert!(*locked);
+    }
+}
@@ -0,0 +1,205 @@
+use std::sync::mpsc::Sender;
+use rand::Rng;
+use crate::database::db_thread::DbCommand;
+use crate::database::item_config::ItemConfig;
+use crate::database::world::World;
+use crate::game::state::{GameState, Sessions};
+use crate::network::host::Host;
+use crate::network::packet::{GamePacket, VariantListBuilder};
+use crate::network::server::{broadcast_to_world, send_console_message, send_variant};
+
+pub const GEM_ITEM_ID: u16 = 112;
+pub const PICKUP_RANGE: f32 = 48.0;
+
+const SEED_CHANCE: f64 = 0.25;
+const BLOCK_CHANCE: f64 = 0.125;
+const GEM_CHANCE: f64 = 0.4;
+
+pub fn roll_drops(block_id: u16, item_config: impl Fn(i32) -> ItemConfig, rng: &mut impl Rng) -> Vec<(u16, u8)> {
+    let mut drops = Vec::new();
+    let config = item_config(block_id as i32);
+    if config.action_type == crate::game::farming::SEED_ACTION_TYPE { return drops; }
+
+    let seed_id = block_id.saturating_add(1);
+    let has_seed = item_config(seed_id as i32).action_type == crate::game::farming::SEED_ACTION_TYPE;
+    if has_seed && rng.gen_bool(SEED_CHANCE) {
+        drops.push((seed_id, 1));
+    } else if rng.gen_bool(BLOCK_CHANCE) {
+        drops.push((block_id, 1));
+    }
+
+    if config.rarity > 0 && config.rarity < 999 && rng.gen_bool(GEM_CHANCE) {
+        let max_gems = (config.rarity / 8).clamp(1, 200) as u8;
+        drops.push((GEM_ITEM_ID, rng.gen_range(1..=max_gems)));
+    }
+
+    drops
+}
+
+pub fn spawn_drop(host: &mut Host, sessions: &Sessions, world_name: &str, world: &mut World, item_id: u16, count: u8, (x, y): (f32, f32)) -> bool {
+    let Some(object) = world.spawn_object(item_id, count, x, y) else { return false; };
+    let pkt = GamePacket::spawn_object(object.item_id, object.x, object.y, object.count);
+    broadcast_to_world(host, sessions, world_name, &pkt.to_bytes(&[], 0), None, false);
+    true
+}
+
+pub fn spawn_break_drops(host: &mut Host, sessions: &Sessions, world_name: &str, world: &mut World, tile_x: i32, tile_y: i32, block_id: u16) -> bool {
+    let mut rng = rand::thread_rng();
+    let mut spawned = false;
+    for (item_id, count) in roll_drops(block_id, crate::database::item_config::get_item_config, &mut rng) {
+        let x = (tile_x * 32) as f32 + rng.gen_range(4.0..20.0);
+        let y = (tile_y * 32) as f32 + rng.gen_range(4.0..20.0);
+        spawned |= spawn_drop(host, sessions, world_name, world, item_id, count, (x, y));
+    }
+    spawned
+}
+
+pub fn pickup_object(host: &mut Host, state: &mut GameState, db_tx: &Sender<DbCommand>, peer_id: u32, uid: u32) {
+    let Some(world_name) = state.sessions.world(peer_id).map(str::to_string) else { return; };
+    let Some((px, py)) = state.sessions.pos(peer_id) else { return; };
+    let Some(world) = state.active_worlds.get_mut(&world_name) else { return; };
+    let Some(index) = world.find_object(uid) else { return; };
+
+    let object = world.objects[index].clone();
+    if (object.x - px).abs() > PICKUP_RANGE || (object.y - py).abs() > PICKUP_RANGE { return; }
+
+    let item_name = crate::database::item_config::get_item_config(object.item_id as i32).name;
+
+    let taken = if object.item_id == GEM_ITEM_ID {
+        let Some(player) = state.sessions.player_mut(peer_id) else { return; };
+        player.gems += object.count as i32;
+        db_tx.send(DbCommand::UpdatePlayer(player.clone())).ok();
+        let (b_data, b_c) = VariantListBuilder::new().add_string("OnSetBux").add_int(player.gems).build();
+        send_variant(host, peer_id, b_data, b_c, -1, 0);
+        object.count
+    } else {
+        let added = crate::game::inventory::change_item(host, &mut state.sessions, db_tx, peer_id, object.item_id as i32, object.count as i32) as u8;
+        if added == 0 {
+            send_console_message(host, peer_id, &format!("`oYou can't carry any more `w{}``.", item_name));
+            return;
+        }
+        send_console_message(host, peer_id, &format!("`oCollected `w{} {}``.", added, item_name));
+        added
+    };
+
+    let pkt = if taken < object.count {
+        world.objects[index].count -= taken;
+        GamePacket::modify_object(uid, object.item_id, object.x, object.y, object.count - taken)
+    } else {
+        world.objects.remove(index);
+        GamePacket::collect_object(peer_id as i32, uid)
+    };
+    db_tx.send(DbCommand::UpdateWorld(world.clone())).ok();
+    broadcast_to_world(host, &state.sessions, &world_name, &pkt.to_bytes(&[], 0), None, false);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use crate::database::item_config::get_item_config;
+    use crate::database::player::{Player, MAX_STACK};
+    use crate::game::farming::SEED_ACTION_TYPE;
+    use crate::network::handler::test_support::TestServer;
+
+    const BLOCK: u16 = 2;
+    const SEED: u16 = 3;
+
+    fn items(id: i32) -> ItemConfig {
+        let mut config = get_item_config(id);
+        match id as u16 {
+            BLOCK => config.rarity = 80,
+            SEED => config.action_type = SEED_ACTION_TYPE,
+            _ => {}
+        }
+        config
+    }
+
+    #[test]
+    fn blocks_drop_either_a_seed_or_the_block() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let (mut seeds, mut blocks) = (0, 0);
+        for _ in 0..1000 {
+            let drops = roll_drops(BLOCK, items, &mut rng);
+            let seed = drops.contains(&(SEED, 1));
+            let block = drops.contains(&(BLOCK, 1));
+            assert!(!(seed && block));
+            seeds += seed as u32;
+            blocks += block as u32;
+        }
+        assert!(seeds > 0 && blocks > 0);
+    }
+
+    #[test]
+    fn seeds_never_drop_anything() {
+        let mut rng = StdRng::seed_from_u64(7);
+        assert!((0..1000).all(|_| roll_drops(SEED, items, &mut rng).is_empty()));
+    }
+
+    #[test]
+    fn gem_count_is_bounded_by_rarity() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let gems: Vec<u8> = (0..1000)
+            .flat_map(|_| roll_drops(BLOCK, items, &mut rng))
+            .filter(|&(id, _)| id == GEM_ITEM_ID)
+            .map(|(_, count)| count)
+            .collect();
+        assert!(!gems.is_empty());
+        assert!(gems.iter().all(|&count| (1..=10).contains(&count)));
+        assert!(gems.contains(&10));
+
+        let mut common = items(BLOCK as i32);
+        common.rarity = 0;
+        assert!((0..1000).all(|_| roll_drops(BLOCK, |_| common.clone(), &mut rng).iter().all(|&(id, _)| id != GEM_ITEM_ID)));
+    }
+
+    fn server_at(pos: (f32, f32), held: i32) -> TestServer {
+        let mut server = TestServer::new();
+        let mut player = Player::new("tester");
+        player.add_item(BLOCK as i32, held);
+        server.join(1, player, World::blank("FLOOR", 30, 30));
+        server.state.sessions.entry(1).pos = pos;
+        server
+    }
+
+    fn pickup(server: &mut TestServer, item_id: u16, count: u8) -> u32 {
+        let uid = server.state.active_worlds.get_mut("FLOOR").unwrap().spawn_object(item_id, count, 100.0, 100.0).unwrap().uid;
+        pickup_object(&mut server.host, &mut server.state, &server.db_tx, 1, uid);
+        uid
+    }
+
+    #[test]
+    fn pickup_requires_being_in_range() {
+        let mut server = server_at((100.0 + PICKUP_RANGE + 1.0, 100.0), 0);
+        pickup(&mut server, BLOCK, 5);
+        assert_eq!(server.world("FLOOR").objects.len(), 1);
+        assert_eq!(server.state.sessions.player(1).unwrap().item_count(BLOCK as i32), 0);
+
+        server.state.sessions.entry(1).pos = (100.0 + PICKUP_RANGE, 100.0);
+        pickup_object(&mut server.host, &mut server.state, &server.db_tx, 1, 1);
+        assert!(server.world("FLOOR").objects.is_empty());
+        assert_eq!(server.state.sessions.player(1).unwrap().item_count(BLOCK as i32), 5);
+    }
+
+    #[test]
+    fn partial_pickup_leaves_the_rest_on_the_ground() {
+        let mut server = server_at((100.0, 100.0), MAX_STACK - 3);
+        let uid = pickup(&mut server, BLOCK, 10);
+        let objects = &server.world("FLOOR").objects;
+        assert_eq!(objects.len(), 1);
+        assert_eq!((objects[0].uid, objects[0].count), (uid, 7));
+        assert_eq!(server.state.sessions.player(1).unwrap().item_count(BLOCK as i32), MAX_STACK);
+    }
+
+    #[test]
+    fn gems_are_added_to_the_balance() {
+        let mut server = server_at((100.0, 100.0), 0);
+        server.state.sessions.player_mut(1).unwrap().gems = 5;
+        pickup(&mut server, GEM_ITEM_ID, 20);
+        assert!(server.world("FLOOR").objects.is_empty());
+        let player = server.state.sessions.player(1).unwrap();
+        assert_eq!(player.gems, 25);
+        assert_eq!(player.item_count(GEM_ITEM_ID as i32), 0);
+    }
+}
@@ -0,0 +1,88 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use rand::Rng;
+use crate::database::tile_extra::TileExtra;
+use crate::database::world::World;
+use crate::network::host::Host;
+use crate::network::packet::VariantListBuilder;
+use crate::network::server::send_variant;
+
+pub const SEED_ACTION_TYPE: u8 = 19;
+pub const MAX_FRUIT: u8 = 4;
+
+pub fn fruit_for_seed(seed_id: u16) -> u16 {
+    seed_id.saturating_sub(1)
+}
+
+pub fn plant(now: u64) -> TileExtra {
+    let fruit_count = rand::thread_rng().gen_range(1..=MAX_FRUIT);
+    TileExtra::Seed { planted_at: now, fruit_count }
+}
+
+pub fn is_ripe(planted_at: u64, grow_time: u32, now: u64) -> bool {
+    now.saturating_sub(planted_at) >= grow_time as u64
+}
+
+pub fn time_left(planted_at: u64, grow_time: u32, now: u64) -> u64 {
+    (planted_at + grow_time as u64).saturating_sub(now)
+}
+
+pub fn format_duration(secs: u64) -> String {
+    let (days, hours, mins) = (secs / 86400, secs % 86400 / 3600, secs % 3600 / 60);
+    match (days, hours, mins) {
+        (0, 0, 0) => format!("{} secs", secs),
+        (0, 0, m) => format!("{} mins, {} secs", m, secs % 60),
+        (0, h, m) => format!("{} hours, {} mins", h, m),
+        (d, h, _) => format!("{} days, {} hours", d, h),
+    }
+}
+
+pub fn show_tree_info(host: &mut Host, world: &World, peer_id: u32, index: usize) -> bool {
+    let Some(tile) = world.tiles.get(index) else { return false; };
+    let Some(TileExtra::Seed { planted_at, fruit_count }) = tile.extra else { return false; };
+    let config = crate::database::item_config::get_item_config(tile.fg as i32);
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+
+    let msg = if is_ripe(planted_at, config.grow_time, now) {
+        format!("`w{}`` - `2Ripe!`` ({} fruit)", config.name, fruit_count)
+    } else {
+        format!("`w{}`` - ripe in `w{}``", config.name, format_duration(time_left(planted_at, config.grow_time, now)))
+    };
+
+    let (b_data, b_c) = VariantListBuilder::new()
+        .add_string("OnTalkBubble").add_int(peer_id as i32).add_string(&msg).add_int(0).build();
+    send_variant(host, peer_id, b_data, b_c, -1, 0);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ripeness_follows_grow_time() {
+        assert!(!is_ripe(1000, 60, 1059));
+        assert!(is_ripe(1000, 60, 1060));
+        assert!(is_ripe(1000, 0, 1000));
+        assert!(!is_ripe(2000, 60, 1000));
+        assert_eq!(time_left(1000, 60, 1030), 30);
+        assert_eq!(time_left(1000, 60, 5000), 0);
+    }
+
+    #[test]
+    fn planted_seeds_bear_fruit() {
+        for _ in 0..100 {
+            let TileExtra::Seed { planted_at, fruit_count } = plant(42) else { panic!("not a seed") };
+            assert_eq!(planted_at, 42);
+            assert!((1..=MAX_FRUIT).contains(&fruit_count));
+        }
+        assert_eq!(fruit_for_seed(3), 2);
+    }
+
+    #[test]
+    fn durations_are_readable() {
+        assert_eq!(format_duration(42), "42 secs");
+        assert_eq!(format_duration(125), "2 mins, 5 secs");
+        assert_eq!(format_duration(3 * 3600 + 60), "3 hours, 1 mins");
+        assert_eq!(format_duration(2 * 86400 + 7200), "2 days, 2 hours");
+    }
+}
@@ -29,8 +29,21 @@ pub fn get_block_xp(item_id: u32) -> u32 {
     }
 }
 
+pub fn get_harvest_xp(grow_time: u32, fruit_count: u8) -> u32 {
+
+    fruit_count as u32 * (1 + (grow_time / 3600).min(24))
+}
+
 pub fn check_farmer_xp(player: &mut crate::database::player::Player, block_id: u32) -> (u32, bool) {
-    let xp_gain = get_block_xp(block_id);
+    add_farmer_xp(player, get_block_xp(block_id))
+}
+
+pub fn check_harvest_xp(player: &mut crate::database::player::Player, seed_id: u16, fruit_count: u8) -> (u32, bool) {
+    let grow_time = crate::database::item_config::get_item_config(seed_id as i32).grow_time;
+    add_farmer_xp(player, get_harvest_xp(grow_time, fruit_count))
+}
+
+pub fn add_farmer_xp(player: &mut crate::database::player::Player, xp_gain: u32) -> (u32, bool) {
     if xp_gain == 0 {
         return (0, false);
     }
@@ -1,102 +1,227 @@
+use std::fmt::Display;
 use crate::database::player;
+use crate::network::handler::{ActionContext, ActionRegistry};
+use crate::network::packet::{TextPacket, VariantListBuilder};
+use crate::network::server::send_variant;
 
-pub fn build_role_menu(player: &player::Player, active_tab: &str, net_id: i32) -> String {
-    let mut dialog = String::from("set_default_color|`o\n");
-    dialog.push_str(&format!("embed_data|netID|{}|\n", net_id));
-    dialog.push_str("add_popup_name|role_menu|\n");
+pub fn register_handlers(registry: &mut ActionRegistry) {
+    registry.register_action("wrench", handle_wrench);
+    registry.register_dialog("role_menu", handle_role_menu);
+    registry.register_dialog("popup", handle_popup);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Size {
+    Small,
+    Big,
+}
+
+impl Size {
+    fn as_str(self) -> &'static str {
+        match self {
+            Size::Small => "small",
+            Size::Big => "big",
+        }
+    }
+}
+
+pub fn escape(value: &str) -> String {
+    value.chars()
+        .filter(|c| *c != '|' && *c != '\0')
+        .map(|c| if c == '\n' || c == '\r' { ' ' } else { c })
+        .collect()
+}
 
+#[derive(Debug, Default, Clone)]
+pub struct DialogBuilder {
+    dialog: String,
+}
 
-    dialog.push_str("start_custom_tabs|\n");
+impl DialogBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-    let f_human = if active_tab == "roleTab_human" { "1,0" } else { "0,0" };
+    fn line(mut self, fields: &[&dyn Display]) -> Self {
+        for (i, field) in fields.iter().enumerate() {
+            if i == 0 {
+                self.dialog.push_str(&field.to_string());
+            } else {
+                self.dialog.push('|');
+                self.dialog.push_str(&escape(&field.to_string()));
+            }
+        }
+        self.dialog.push_str("|\n");
+        self
+    }
 
+    pub fn default_color(self, color: &str) -> Self {
+        self.line(&[&"set_default_color", &color])
+    }
 
-    dialog.push_str("add_spacer|small|\n");
-    dialog.push_str("add_label_with_icon|big|`wROLE SYSTEM                                        ``|left|1366|\n");
-    dialog.push_str("add_textbox|`o``|left|\n");
-    dialog.push_str("add_spacer|small|\n");
+    pub fn embed_data(self, key: &str, value: impl Display) -> Self {
+        self.line(&[&"embed_data", &key, &value])
+    }
 
+    pub fn popup_name(self, name: &str) -> Self {
+        self.line(&[&"add_popup_name", &name])
+    }
 
-    match active_tab {
+    pub fn label_with_icon(self, size: Size, text: &str, icon: u16) -> Self {
+        self.line(&[&"add_label_with_icon", &size.as_str(), &text, &"left", &icon])
+    }
 
-        _ => {
+    pub fn textbox(self, text: &str) -> Self {
+        self.line(&[&"add_textbox", &text, &"left"])
+    }
 
-            dialog.push_str("add_textbox|`wRole: `oHuman``|left|\n");
-            dialog.push_str(&format!(
-                "add_textbox|`wName: `o{}``|left|\n",
-                player.name
-            ));
-            dialog.push_str(&format!(
-                "add_textbox|`wGems: `2{}``|left|\n",
-                player.gems
-            ));
-            dialog.push_str(&format!(
-                "add_textbox|`wFarming Level: `5{}``|left|\n",
-                player.farmer_lvl
-            ));
+    pub fn button(self, name: &str, label: &str) -> Self {
+        self.line(&[&"add_button", &name, &label, &"noflags", &0, &0])
+    }
+
+    pub fn custom_button(self, name: &str, style: &str) -> Self {
+        self.line(&[&"add_custom_button", &name, &style])
+    }
+
+    pub fn custom_spacing(self, x: i32, y: i32) -> Self {
+        self.line(&[&"set_custom_spacing", &format!("x:{};y:{}", x, y)])
+    }
+
+    pub fn custom_break(self) -> Self {
+        self.line(&[&"add_custom_break"])
+    }
+
+    pub fn spacer(self, size: Size) -> Self {
+        self.line(&[&"add_spacer", &size.as_str()])
+    }
+
+    pub fn text_input(self, name: &str, label: &str, default: &str, max_len: u32) -> Self {
+        self.line(&[&"add_text_input", &name, &label, &default, &max_len])
+    }
+
+    pub fn checkbox(self, name: &str, label: &str, checked: bool) -> Self {
+        self.line(&[&"add_checkbox", &name, &label, &(checked as u8)])
+    }
+
+    pub fn tabs(mut self, tabs: &[(&str, &str)], active: &str) -> Self {
+        self = self.line(&[&"start_custom_tabs"]);
+        for (name, label) in tabs {
+            let state = if *name == active { 1 } else { 0 };
+            let style = format!("textLabel:{};state:{};", escape(label).replace(';', ","), state);
+            self = self.custom_button(name, &style);
         }
+        self.line(&[&"end_custom_tabs"])
     }
 
-    dialog.push_str("add_textbox|`o``|left|\n");
-    dialog.push_str("add_spacer|small|\n");
-    dialog.push_str("end_dialog|role_menu||Back|\n");
-    dialog.push_str("add_quick_exit|\n");
+    pub fn player_info(self, name: &str, level: impl Display, xp: impl Display, next_level_xp: impl Display) -> Self {
+        self.line(&[&"add_player_info", &name, &level, &xp, &next_level_xp])
+    }
 
-    dialog
-}
+    pub fn quick_exit(self) -> Self {
+        self.line(&[&"add_quick_exit"])
+    }
 
-pub fn build_profile_menu(player: &player::Player, net_id: i32) -> String {
-    let mut dialog = String::from("set_default_color|`o\n");
-    dialog.push_str(&format!("embed_data|netID|{}|\n", net_id));
-    dialog.push_str("add_popup_name|profile_menu|\n");
-    dialog.push_str("add_label_with_icon|big|`wProfile``|left|1366|\n");
-    dialog.push_str("add_spacer|small|\n");
-    dialog.push_str(&format!("add_textbox|`wName: `o{}``|left|\n", player.name));
-    dialog.push_str(&format!("add_textbox|`wGems: `2{}``|left|\n", player.gems));
-    dialog.push_str(&format!("add_textbox|`wLevel: `5{}``|left|\n", player.level));
-    dialog.push_str("add_spacer|small|\n");
-    dialog.push_str("add_button|set_online_status|Set Status|noflags|0|0|\n");
-    dialog.push_str("end_dialog|profile_menu|Cancel|OK|\n");
-    dialog.push_str("add_quick_exit|\n");
-    dialog
+    pub fn end_dialog(self, name: &str, cancel: &str, ok: &str) -> Self {
+        self.line(&[&"end_dialog", &name, &cancel, &ok])
+    }
+
+    pub fn build(self) -> String {
+        self.dialog
+    }
 }
 
-pub fn build_farmer_menu(player: &player::Player, net_id: i32) -> String {
-    let mut dialog = String::from("set_default_color|`o\n");
-    dialog.push_str(&format!("embed_data|netID|{}|\n", net_id));
-    dialog.push_str("add_popup_name|WrenchMenu|\n");
+pub fn build_role_menu(player: &player::Player, active_tab: &str, net_id: i32) -> String {
+    let dialog = DialogBuilder::new()
+        .default_color("`o")
+        .embed_data("netID", net_id)
+        .popup_name("role_menu")
+        .tabs(&[("roleTab_human", "Human")], active_tab)
+        .spacer(Size::Small)
+        .label_with_icon(Size::Big, "`wROLE SYSTEM                                        ``", 1366)
+        .textbox("`o``")
+        .spacer(Size::Small);
 
-    let next_level_xp = crate::game::gt_mmo::get_xp_required(player.farmer_lvl as u32);
 
+    let dialog = match active_tab {
 
-    dialog.push_str(&format!(
-        "add_player_info|`2[{}]``|{}|{}|{}|\n",
-        player.name,
-        player.farmer_lvl,
-        player.farmer_xp,
-        next_level_xp
-    ));
+        _ => dialog
+            .textbox("`wRole: `oHuman``")
+            .textbox(&format!("`wName: `o{}``", player.name))
+            .textbox(&format!("`wGems: `2{}``", player.gems))
+            .textbox(&format!("`wFarming Level: `5{}``", player.farmer_lvl)),
+    };
 
-    dialog.push_str("add_spacer|small|\n");
-    dialog.push_str("set_custom_spacing|x:5;y:10|\n");
+    dialog
+        .textbox("`o``")
+        .spacer(Size::Small)
+        .end_dialog("role_menu", "", "Back")
+        .quick_exit()
+        .build()
+}
 
-    dialog.push_str("add_custom_button|goals|image:interface/large/gui_wrench_goals_quests.rttex;image_size:400,260;width:0.19;|\n");
+pub fn build_profile_menu(player: &player::Player, net_id: i32) -> String {
+    DialogBuilder::new()
+        .default_color("`o")
+        .embed_data("netID", net_id)
+        .popup_name("profile_menu")
+        .label_with_icon(Size::Big, "`wProfile``", 1366)
+        .spacer(Size::Small)
+        .textbox(&format!("`wName: `o{}``", player.name))
+        .textbox(&format!("`wGems: `2{}``", player.gems))
+        .textbox(&format!("`wLevel: `5{}``", player.level))
+        .spacer(Size::Small)
+        .button("set_online_status", "Set Status")
+        .end_dialog("profile_menu", "Cancel", "OK")
+        .quick_exit()
+        .build()
+}
 
-    dialog.push_str("add_custom_break|\n");
-    dialog.push_str("add_spacer|small|\n");
-    dialog.push_str("set_custom_spacing|x:0;y:0|\n");
+pub fn build_player_menu(name: &str, net_id: u32, can_moderate: bool) -> String {
+    let dialog = DialogBuilder::new()
+        .default_color("`o")
+        .label_with_icon(Size::Big, &format!("`w{}``", name), 18)
+        .spacer(Size::Small)
+        .textbox("This is a player.");
 
-    dialog.push_str("end_dialog|popup||Continue|\n");
-    dialog.push_str("add_quick_exit|\n");
+    if !can_moderate {
+        return dialog.end_dialog("profile", "OK", "").build();
+    }
     dialog
+        .embed_data("netID", net_id)
+        .spacer(Size::Small)
+        .button("pull", "`5Pull``")
+        .button("kick", "`4Kick``")
+        .button("ban", "`4World Ban``")
+        .end_dialog("player_moderate", "Close", "")
+        .build()
+}
+
+pub fn build_farmer_menu(player: &player::Player, net_id: i32) -> String {
+    let next_level_xp = crate::game::gt_mmo::get_xp_required(player.farmer_lvl as u32);
+
+    DialogBuilder::new()
+        .default_color("`o")
+        .embed_data("netID", net_id)
+        .popup_name("WrenchMenu")
+        .player_info(&format!("`2[{}]``", player.name), player.farmer_lvl, player.farmer_xp, next_level_xp)
+        .spacer(Size::Small)
+        .custom_spacing(5, 10)
+        .custom_button("goals", "image:interface/large/gui_wrench_goals_quests.rttex;image_size:400,260;width:0.19;")
+        .custom_break()
+        .spacer(Size::Small)
+        .custom_spacing(0, 0)
+        .end_dialog("popup", "", "Continue")
+        .quick_exit()
+        .build()
 }
 
 pub fn build_milestones_menu(player: &player::Player, net_id: i32) -> String {
-    let mut dialog = String::from("set_default_color|`o\n");
-    dialog.push_str(&format!("embed_data|netID|{}|\n", net_id));
-    dialog.push_str("add_popup_name|milestones_menu|\n");
-    dialog.push_str("add_label_with_icon|big|`wFarmer Milestones``|left|1366|\n");
-    dialog.push_str("add_spacer|small|\n");
+    let mut dialog = DialogBuilder::new()
+        .default_color("`o")
+        .embed_data("netID", net_id)
+        .popup_name("milestones_menu")
+        .label_with_icon(Size::Big, "`wFarmer Milestones``", 1366)
+        .spacer(Size::Small);
 
     let current_lvl = player.farmer_lvl as u32;
 
@@ -106,11 +231,97 @@ pub fn build_milestones_menu(player: &player::Player, net_id: i32) -> String {
     for &lvl in &milestones {
         let title = crate::game::gt_mmo::get_milestone_title(lvl).unwrap_or("Unknown");
         let status = if current_lvl >= lvl { "`2(Unlocked)``" } else { "`4(Locked)``" };
-        dialog.push_str(&format!("add_textbox|Level {}: {} {}|left|\n", lvl, title, status));
+        dialog = dialog.textbox(&format!("Level {}: {} {}", lvl, title, status));
     }
 
-    dialog.push_str("add_spacer|small|\n");
-    dialog.push_str("end_dialog|milestones|OK||\n");
-    dialog.push_str("add_quick_exit|\n");
     dialog
-}
\ No newline at end of file
+        .spacer(Size::Small)
+        .end_dialog("milestones", "OK", "")
+        .quick_exit()
+        .build()
+}
+
+pub fn build_gazette() -> String {
+    DialogBuilder::new()
+        .spacer(Size::Small)
+        .label_with_icon(Size::Big, "`wThe Growtopia Rust Server``", 5016)
+        .spacer(Size::Small)
+        .textbox("`wFebruary 3rd: `5First Build``")
+        .spacer(Size::Small)
+        .textbox("Welcome to the new Rust server! ")
+        .quick_exit()
+        .end_dialog("gazette", "", "OK")
+        .build()
+}
+
+fn handle_wrench(ctx: &mut ActionContext<'_>, text: &TextPacket) {
+    let peer_id = ctx.peer_id;
+    let target_net_id = text.get_parsed::<u32>("netid").unwrap_or(0);
+
+    if target_net_id == peer_id {
+
+        if let Some(player) = ctx.state.sessions.player(peer_id) {
+            let menu = build_profile_menu(player, peer_id as i32);
+            let (d_data, d_c) = VariantListBuilder::new()
+                .add_string("OnDialogRequest").add_string(&menu).build();
+            send_variant(ctx.host, peer_id, d_data, d_c, -1, 0);
+        }
+    } else {
+
+        let can_moderate = crate::game::moderation::can_moderate(ctx, target_net_id);
+        let target_name = ctx.state.sessions.name(target_net_id).unwrap_or("Unknown");
+        let dialog = build_player_menu(target_name, target_net_id, can_moderate);
+        let (d_data, d_c) = VariantListBuilder::new()
+            .add_string("OnDialogRequest").add_string(&dialog).build();
+        send_variant(ctx.host, peer_id, d_data, d_c, -1, 0);
+    }
+}
+
+fn handle_role_menu(ctx: &mut ActionContext<'_>, text: &TextPacket) {
+    let peer_id = ctx.peer_id;
+    if let Some(button_clicked) = text.get("buttonClicked")
+        && button_clicked.starts_with("roleTab_")
+        && let Some(player) = ctx.state.sessions.player(peer_id) {
+        let menu = build_role_menu(player, button_clicked, peer_id as i32);
+        let (d_data, d_c) = VariantListBuilder::new()
+            .add_string("OnDialogRequest").add_string(&menu).build();
+        send_variant(ctx.host, peer_id, d_data, d_c, -1, 0);
+    }
+}
+
+fn handle_popup(ctx: &mut ActionContext<'_>, text: &TextPacket) {
+    let peer_id = ctx.peer_id;
+    if let Some(button_clicked) = text.get("buttonClicked")
+        && button_clicked == "goals"
+        && let Some(player) = ctx.state.sessions.player(peer_id) {
+        let menu = build_milestones_menu(player, peer_id as i32);
+        let (d_data, d_c) = VariantListBuilder::new()
+            .add_string("OnDialogRequest").add_string(&menu).build();
+        send_variant(ctx.host, peer_id, d_data, d_c, -1, 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_strips_separators() {
+        assert_eq!(escape("a|b\nc\r\0d"), "ab c d");
+        assert_eq!(escape("`wPlain`` text"), "`wPlain`` text");
+    }
+
+    #[test]
+    fn player_text_cannot_inject_dialog_lines() {
+        let name = "evil|\nadd_button|steal|Free gems|noflags|0|0";
+        let dialog = build_player_menu(name, 5, false);
+        assert_eq!(dialog.lines().count(), 5);
+        assert!(!dialog.lines().any(|l| l.starts_with("add_button")));
+        assert!(dialog.contains("add_label_with_icon|big|`wevil add_buttonstealFree gemsnoflags00``|left|18|\n"));
+
+        let dialog = DialogBuilder::new()
+            .text_input("sign_text", "Text:", "hi|there\nend_dialog|x", 100)
+            .build();
+        assert_eq!(dialog, "add_text_input|sign_text|Text:|hithere end_dialogx|100|\n");
+    }
+}
@@ -0,0 +1,24 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::game::tick::TickScheduler;
+use crate::network::packet::GamePacket;
+use crate::network::server::send_packet;
+
+const HEAL_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+pub fn register_systems(scheduler: &mut TickScheduler, default_delay: Duration) {
+    let check_every = scheduler.ticks_for(HEAL_CHECK_INTERVAL);
+    let default_delay_ms = default_delay.as_millis() as u64;
+
+    scheduler.register_world_system(move |ctx| {
+        if ctx.tick % check_every != 0 || ctx.world.damage_times.is_empty() { return; }
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+        for index in ctx.world.heal_damage(now, default_delay_ms) {
+            let (x, y) = ctx.world.tile_pos(index);
+            let update = GamePacket::send_tile_update(x as i32, y as i32).to_bytes_with_raw_data(&ctx.world.tile_to_bytes(index));
+            for &p_id in ctx.peers {
+                send_packet(ctx.host, p_id, update.clone());
+            }
+        }
+    });
+}
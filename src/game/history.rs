@@ -0,0 +1,318 @@
+use std::collections::BTreeMap;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{error, info};
+use crate::database::db_thread::DbCommand;
+use crate::database::tile_history::{EditQuery, TileEdit};
+use crate::database::world::{TileChangeResult, World};
+use crate::game::state::GameState;
+use crate::game::tick::TickScheduler;
+use crate::network::handler::ActionContext;
+use crate::network::host::Host;
+use crate::network::packet::GamePacket;
+use crate::network::server::{broadcast_to_world, send_console_message};
+
+pub const HISTORY_LINES: usize = 10;
+pub const MAX_ROLLBACK_MINUTES: u64 = 7 * 24 * 60;
+const QUERY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug)]
+enum EditRequest {
+    History { x: i32, y: i32 },
+    Rollback { world: String, target: String, actor: String },
+}
+
+#[derive(Debug)]
+struct PendingQuery {
+    peer_id: Option<u32>,
+    request: EditRequest,
+    reply: Receiver<Result<Vec<TileEdit>, String>>,
+}
+
+#[derive(Debug, Default)]
+pub struct PendingQueries {
+    queries: Vec<PendingQuery>,
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+pub fn register_systems(scheduler: &mut TickScheduler, db_tx: Sender<DbCommand>) {
+    scheduler.every(QUERY_POLL_INTERVAL, move |ctx| poll_queries(ctx.host, ctx.state, &db_tx));
+}
+
+fn query(state: &mut GameState, db_tx: &Sender<DbCommand>, peer_id: Option<u32>, query: EditQuery, request: EditRequest) -> Result<(), String> {
+    let (reply_tx, reply) = mpsc::channel();
+    db_tx.send(DbCommand::QueryEdits(query, reply_tx)).map_err(|_| "The database is not available right now.".to_string())?;
+    state.edit_queries.queries.push(PendingQuery { peer_id, request, reply });
+    Ok(())
+}
+
+pub fn poll_queries(host: &mut Host, state: &mut GameState, db_tx: &Sender<DbCommand>) {
+    for pending in std::mem::take(&mut state.edit_queries.queries) {
+        match pending.reply.try_recv() {
+            Ok(result) => finish(host, state, db_tx, pending.peer_id, pending.request, result),
+            Err(TryRecvError::Empty) => state.edit_queries.queries.push(pending),
+            Err(TryRecvError::Disconnected) => {
+                finish(host, state, db_tx, pending.peer_id, pending.request, Err("no reply from the database".to_string()));
+            }
+        }
+    }
+}
+
+fn finish(host: &mut Host, state: &mut GameState, db_tx: &Sender<DbCommand>, peer_id: Option<u32>, request: EditRequest, result: Result<Vec<TileEdit>, String>) {
+    match request {
+        EditRequest::History { x, y } => {
+            let Some(peer_id) = peer_id else { return; };
+            match result {
+                Ok(edits) if edits.is_empty() => send_console_message(host, peer_id, &format!("`oNo edits recorded at {}, {}.``", x, y)),
+                Ok(edits) => {
+                    send_console_message(host, peer_id, &format!("`wHistory of tile {}, {}:``", x, y));
+                    for edit in &edits {
+                        send_console_message(host, peer_id, &describe(edit));
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to read tile history: {}", e);
+                    send_console_message(host, peer_id, "`4Could not read the history right now.``");
+                }
+            }
+        }
+        EditRequest::Rollback { world, target, actor } => {
+            let restored = result
+                .map_err(|e| format!("Failed to read edit history: {}", e))
+                .and_then(|edits| apply_rollback(host, state, db_tx, &world, &actor, &edits));
+            match (peer_id, restored) {
+                (Some(peer_id), Ok(restored)) => send_console_message(host, peer_id, &format!("`oRolled back `w{}`` tiles edited by `w{}``.", restored, target)),
+                (Some(peer_id), Err(e)) => send_console_message(host, peer_id, &format!("`4{}``", e)),
+                (None, Ok(restored)) => info!("Rolled back {} tiles edited by {} in {}", restored, target, world),
+                (None, Err(e)) => info!("Rollback failed: {}", e),
+            }
+        }
+    }
+}
+
+pub fn edit_states(world: &World, x: i32, y: i32, result: TileChangeResult) -> Option<((u16, u16), (u16, u16))> {
+    let tile = &world.tiles[world.tile_index(x, y)?];
+    let after = (tile.fg, tile.bg);
+    let before = match result {
+        TileChangeResult::PlacedFG(_) => (0, after.1),
+        TileChangeResult::PlacedBG(_) => (after.0, 0),
+        TileChangeResult::BrokeFG(id, _) | TileChangeResult::Harvested(id, _) => (id, after.1),
+        TileChangeResult::BrokeBG(id, _) => (after.0, id),
+        _ => return None,
+    };
+    Some((before, after))
+}
+
+pub fn log_edit(db_tx: &Sender<DbCommand>, world: &World, player: &str, index: usize, before: (u16, u16)) {
+    let (x, y) = world.tile_pos(index);
+    let tile = &world.tiles[index];
+    db_tx.send(DbCommand::LogTileEdit(TileEdit {
+        world: world.name.clone(),
+        x,
+        y,
+        player: player.to_string(),
+        at: now(),
+        before,
+        after: (tile.fg, tile.bg),
+    })).ok();
+}
+
+pub fn record(db_tx: &Sender<DbCommand>, world: &World, player: &str, x: i32, y: i32, result: TileChangeResult) {
+    let Some((before, _)) = edit_states(world, x, y, result) else { return; };
+    let Some(index) = world.tile_index(x, y) else { return; };
+    log_edit(db_tx, world, player, index, before);
+}
+
+pub fn plan_rollback(edits: &[TileEdit]) -> Vec<TileEdit> {
+    let mut tiles: BTreeMap<(u32, u32), TileEdit> = BTreeMap::new();
+    for edit in edits {
+        tiles.entry((edit.x, edit.y))
+            .and_modify(|merged| merged.after = edit.after)
+            .or_insert_with(|| edit.clone());
+    }
+    tiles.into_values().collect()
+}
+
+fn ensure_loaded(state: &mut GameState, world_name: &str) -> Result<(), String> {
+    if state.active_worlds.contains_key(world_name) { return Ok(()); }
+    match crate::database::world::load_world(world_name) {
+        Ok(Some(world)) => {
+            state.active_worlds.insert(world_name.to_string(), world);
+            Ok(())
+        }
+        Ok(None) => Err(format!("World {} does not exist.", world_name)),
+        Err(e) => Err(format!("Failed to load {}: {}", world_name, e)),
+    }
+}
+
+pub fn rollback(state: &mut GameState, db_tx: &Sender<DbCommand>, peer_id: Option<u32>, world_name: &str, target: &str, minutes: u64, actor: &str) -> Result<(), String> {
+    let minutes = minutes.clamp(1, MAX_ROLLBACK_MINUTES);
+    ensure_loaded(state, world_name)?;
+
+    let since = now().saturating_sub(minutes * 60);
+    let edits = EditQuery::PlayerSince { world: world_name.to_string(), player: target.to_string(), since };
+    let request = EditRequest::Rollback { world: world_name.to_string(), target: target.to_string(), actor: actor.to_string() };
+    query(state, db_tx, peer_id, edits, request)
+}
+
+fn apply_rollback(host: &mut Host, state: &mut GameState, db_tx: &Sender<DbCommand>, world_name: &str, actor: &str, edits: &[TileEdit]) -> Result<usize, String> {
+    ensure_loaded(state, world_name)?;
+    let Some(world) = state.active_worlds.get_mut(world_name) else { return Ok(0); };
+    let mut restored = 0;
+    for TileEdit { x, y, before, after, .. } in plan_rollback(edits) {
+        let Some(index) = world.tile_index(x as i32, y as i32) else { continue; };
+        let current = (world.tiles[index].fg, world.tiles[index].bg);
+        if current != after || !world.restore_tile(index, before) { continue; }
+        restored += 1;
+
+        log_edit(db_tx, world, actor, index, current);
+        let update = GamePacket::send_tile_update(x as i32, y as i32).to_bytes_with_raw_data(&world.tile_to_bytes(index));
+        broadcast_to_world(host, &state.sessions, world_name, &update, None, false);
+    }
+
+    if restored > 0 {
+        db_tx.send(DbCommand::UpdateWorld(world.clone())).ok();
+    }
+    Ok(restored)
+}
+
+fn describe(edit: &TileEdit) -> String {
+    let name = |id: u16| crate::database::item_config::get_item_config(id as i32).name;
+    let (action, item) = if edit.before.0 != edit.after.0 {
+        if edit.before.0 == 0 { ("placed", edit.after.0) } else { ("removed", edit.before.0) }
+    } else if edit.before.1 == 0 {
+        ("placed", edit.after.1)
+    } else {
+        ("removed", edit.before.1)
+    };
+    format!("`w{}`` {} `w{}`` {} ago", edit.player, action, name(item), crate::game::farming::format_duration(now().saturating_sub(edit.at)))
+}
+
+pub fn handle_command(ctx: &mut ActionContext<'_>, command: &str, args: &[&str]) -> bool {
+    let peer_id = ctx.peer_id;
+    match command {
+        "history" => {
+            let Some(world_name) = ctx.state.sessions.world(peer_id).map(str::to_string) else { return true; };
+            let Some(player) = ctx.state.sessions.player(peer_id) else { return true; };
+            let Some(world) = ctx.state.active_worlds.get(&world_name) else { return true; };
+            if !world.has_access(&player.name) && !player.is_admin() {
+                send_console_message(ctx.host, peer_id, "`4You need access to this world's lock to see its history.``");
+                return true;
+            }
+
+            let (px, py) = ctx.state.sessions.pos(peer_id).unwrap_or_default();
+            let x = args.first().and_then(|v| v.parse().ok()).unwrap_or(((px + 10.0) / 32.0) as i32);
+            let y = args.get(1).and_then(|v| v.parse().ok()).unwrap_or(((py + 15.0) / 32.0) as i32);
+            if world.tile_index(x, y).is_none() {
+                send_console_message(ctx.host, peer_id, "`4That tile is outside the world.``");
+                return true;
+            }
+
+            let edits = EditQuery::Tile { world: world_name, x: x as u32, y: y as u32, limit: HISTORY_LINES };
+            if let Err(e) = query(ctx.state, ctx.db_tx, Some(peer_id), edits, EditRequest::History { x, y }) {
+                send_console_message(ctx.host, peer_id, &format!("`4{}``", e));
+            }
+        }
+        "rollback" => {
+            let Some(player) = ctx.state.sessions.player(peer_id) else { return true; };
+            if !player.is_admin() {
+                send_console_message(ctx.host, peer_id, "`4Only admins can roll back edits.``");
+                return true;
+            }
+            let actor = player.name.clone();
+            let (Some(target), Some(minutes)) = (args.first(), args.get(1).and_then(|v| v.parse::<u64>().ok())) else {
+                send_console_message(ctx.host, peer_id, "`4Usage: ``/rollback <player> <minutes>");
+                return true;
+            };
+            let Some(world_name) = ctx.state.sessions.world(peer_id).map(str::to_string) else { return true; };
+
+            if let Err(e) = rollback(ctx.state, ctx.db_tx, Some(peer_id), &world_name, target, minutes, &actor) {
+                send_console_message(ctx.host, peer_id, &format!("`4{}``", e));
+            }
+        }
+        _ => return false,
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::player::Player;
+    use crate::network::handler::test_support::TestServer;
+
+    fn edit(x: u32, before: (u16, u16), after: (u16, u16)) -> TileEdit {
+        TileEdit { world: "TEST".to_string(), x, y: 0, player: "griefer".to_string(), at: 0, before, after }
+    }
+
+    fn answer(db_rx: &Receiver<DbCommand>, edits: Vec<TileEdit>) -> EditQuery {
+        loop {
+            if let DbCommand::QueryEdits(query, reply) = db_rx.try_recv().expect("no edit query sent") {
+                reply.send(Ok(edits)).unwrap();
+                return query;
+            }
+        }
+    }
+
+    #[test]
+    fn rollback_waits_for_the_database_reply() {
+        let mut server = TestServer::new();
+        let (db_tx, db_rx) = mpsc::channel();
+        server.db_tx = db_tx;
+        let mut world = World::blank("TEST", 4, 1);
+        world.tiles[1].fg = 10;
+        server.join(1, Player::new("admin"), world);
+
+        rollback(&mut server.state, &server.db_tx, Some(1), "TEST", "griefer", 5, "admin").unwrap();
+        poll_queries(&mut server.host, &mut server.state, &server.db_tx);
+        assert_eq!(server.world("TEST").tiles[1].fg, 10);
+
+        let query = answer(&db_rx, vec![edit(1, (2, 0), (10, 0)), edit(3, (4, 0), (0, 0))]);
+        assert!(matches!(query, EditQuery::PlayerSince { ref world, ref player, .. } if world == "TEST" && player == "griefer"));
+        poll_queries(&mut server.host, &mut server.state, &server.db_tx);
+
+        let tiles = &server.world("TEST").tiles;
+        assert_eq!((tiles[1].fg, tiles[3].fg), (2, 4));
+        assert!(server.state.edit_queries.queries.is_empty());
+    }
+
+    #[test]
+    fn unanswered_queries_are_dropped_when_the_database_stops() {
+        let mut server = TestServer::new();
+        let (db_tx, db_rx) = mpsc::channel();
+        server.db_tx = db_tx;
+        server.join(1, Player::new("admin"), World::blank("TEST", 4, 1));
+
+        rollback(&mut server.state, &server.db_tx, Some(1), "TEST", "griefer", 5, "admin").unwrap();
+        drop(db_rx);
+        poll_queries(&mut server.host, &mut server.state, &server.db_tx);
+        assert!(server.state.edit_queries.queries.is_empty());
+        assert!(rollback(&mut server.state, &server.db_tx, Some(1), "TEST", "griefer", 5, "admin").is_err());
+    }
+
+    #[test]
+    fn rollback_uses_first_before_and_last_after() {
+        let edits = vec![
+            edit(1, (2, 14), (0, 14)),
+            edit(1, (0, 14), (0, 0)),
+            edit(3, (0, 0), (10, 0)),
+        ];
+        assert_eq!(plan_rollback(&edits), vec![edit(1, (2, 14), (0, 0)), edit(3, (0, 0), (10, 0))]);
+    }
+
+    #[test]
+    fn edit_states_follow_results() {
+        let mut world = World::blank("TEST", 2, 1);
+        world.tiles[0].bg = 14;
+        assert_eq!(edit_states(&world, 0, 0, TileChangeResult::BrokeFG(2, 0)), Some(((2, 14), (0, 14))));
+        assert_eq!(edit_states(&world, 0, 0, TileChangeResult::PlacedBG(14)), Some(((0, 0), (0, 14))));
+        world.tiles[1].fg = 2;
+        assert_eq!(edit_states(&world, 1, 0, TileChangeResult::PlacedFG(2)), Some(((0, 0), (2, 0))));
+        assert_eq!(edit_states(&world, 1, 0, TileChangeResult::BrokeBG(14, 0)), Some(((2, 14), (2, 0))));
+        assert_eq!(edit_states(&world, 1, 0, TileChangeResult::Damaged(2, 1)), None);
+        assert_eq!(edit_states(&world, 5, 0, TileChangeResult::PlacedFG(2)), None);
+    }
+}
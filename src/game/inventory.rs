@@ -0,0 +1,204 @@
+use std::sync::mpsc::Sender;
+use crate::database::db_thread::DbCommand;
+use crate::game::gui::{DialogBuilder, Size};
+use crate::game::state::Sessions;
+use crate::network::handler::{ActionContext, ActionRegistry};
+use crate::network::host::Host;
+use crate::network::packet::{TextPacket, VariantListBuilder};
+use crate::network::server::{broadcast_on_set_clothing, send_console_message, send_inventory, send_variant};
+
+pub fn register_handlers(registry: &mut ActionRegistry) {
+    registry.register_action("drop", handle_drop);
+    registry.register_action("trash", handle_trash);
+    registry.register_dialog("drop_item", handle_drop_item);
+    registry.register_dialog("trash_item", handle_trash_item);
+}
+
+pub fn change_item(host: &mut Host, sessions: &mut Sessions, db_tx: &Sender<DbCommand>, peer_id: u32, item_id: i32, delta: i32) -> i32 {
+    let Some(player) = sessions.player_mut(peer_id) else { return 0; };
+
+    let changed = if delta >= 0 {
+        player.add_item(item_id, delta)
+    } else {
+        player.remove_item(item_id, -delta)
+    };
+    if changed == 0 { return 0; }
+
+    let mut unequipped = false;
+    if player.item_count(item_id) == 0 {
+        for slot in player.equipped.iter_mut().filter(|e| **e == item_id) {
+            *slot = 0;
+            unequipped = true;
+        }
+    }
+
+    db_tx.send(DbCommand::UpdatePlayer(player.clone())).ok();
+    send_inventory(host, peer_id, player);
+    if unequipped {
+        broadcast_on_set_clothing(host, sessions, peer_id);
+    }
+    changed
+}
+
+fn item_dialog(item_id: i32, count: i32, title: &str, prompt: &str, dialog_name: &str) -> String {
+    DialogBuilder::new()
+        .default_color("`o")
+        .label_with_icon(Size::Big, title, item_id as u16)
+        .textbox(prompt)
+        .text_input("count", "", &count.to_string(), 5)
+        .embed_data("itemID", item_id)
+        .end_dialog(dialog_name, "Cancel", "OK")
+        .build()
+}
+
+fn requested_item(ctx: &mut ActionContext<'_>, text: &TextPacket) -> Option<(i32, i32, String)> {
+    let item_id = text.get_parsed::<i32>("itemID")?;
+    let held = ctx.state.sessions.player(ctx.peer_id)?.item_count(item_id);
+    if held <= 0 { return None; }
+
+    let config = crate::database::item_config::get_item_config(item_id);
+    if config.is_untradeable() {
+        send_console_message(ctx.host, ctx.peer_id, &format!("`4You can't get rid of {}.``", config.name));
+        return None;
+    }
+    Some((item_id, held, config.name))
+}
+
+fn can_drop_here(ctx: &mut ActionContext<'_>) -> bool {
+    let Some(world_name) = ctx.state.sessions.world(ctx.peer_id) else { return false; };
+    let Some(world) = ctx.state.active_worlds.get(world_name) else { return false; };
+    let player_name = ctx.state.sessions.name(ctx.peer_id).unwrap_or_default();
+
+    if world.drops_disabled() && !world.has_access(player_name) {
+        send_console_message(ctx.host, ctx.peer_id, "`4The owner of this world has disabled dropping items.``");
+        return false;
+    }
+    if world.objects.len() >= crate::database::world::MAX_OBJECTS {
+        send_console_message(ctx.host, ctx.peer_id, "`4There are too many items on the ground here.``");
+        return false;
+    }
+    true
+}
+
+fn show_dialog(ctx: &mut ActionContext<'_>, dialog: &str) {
+    let (d_data, d_c) = VariantListBuilder::new()
+        .add_string("OnDialogRequest").add_string(dialog).build();
+    send_variant(ctx.host, ctx.peer_id, d_data, d_c, -1, 0);
+}
+
+fn handle_drop(ctx: &mut ActionContext<'_>, text: &TextPacket) {
+    let Some((item_id, held, name)) = requested_item(ctx, text) else { return; };
+    if !can_drop_here(ctx) { return; }
+
+    let dialog = item_dialog(item_id, held, &format!("`wDrop {}``", name), "How many to drop?", "drop_item");
+    show_dialog(ctx, &dialog);
+}
+
+fn handle_trash(ctx: &mut ActionContext<'_>, text: &TextPacket) {
+    let Some((item_id, held, name)) = requested_item(ctx, text) else { return; };
+
+    let prompt = format!("How many to `4destroy``? (you have {})", held);
+    let dialog = item_dialog(item_id, 0, &format!("`4Trash`` `w{}``", name), &prompt, "trash_item");
+    show_dialog(ctx, &dialog);
+}
+
+fn handle_drop_item(ctx: &mut ActionContext<'_>, text: &TextPacket) {
+    let peer_id = ctx.peer_id;
+    let Some((item_id, held, name)) = requested_item(ctx, text) else { return; };
+    let count = text.get_parsed::<i32>("count").unwrap_or(0).min(held);
+    if count <= 0 || !can_drop_here(ctx) { return; }
+
+    let Some(world_name) = ctx.state.sessions.world(peer_id).map(str::to_string) else { return; };
+    let Some((px, py)) = ctx.state.sessions.pos(peer_id) else { return; };
+
+    let removed = change_item(ctx.host, &mut ctx.state.sessions, ctx.db_tx, peer_id, item_id, -count);
+    if removed == 0 { return; }
+
+    let Some(world) = ctx.state.active_worlds.get_mut(&world_name) else { return; };
+    if crate::game::drops::spawn_drop(ctx.host, &ctx.state.sessions, &world_name, world, item_id as u16, removed as u8, (px + 20.0, py)) {
+        ctx.db_tx.send(DbCommand::UpdateWorld(world.clone())).ok();
+    }
+    send_console_message(ctx.host, peer_id, &format!("`oDropped `w{} {}``.", removed, name));
+}
+
+fn handle_trash_item(ctx: &mut ActionContext<'_>, text: &TextPacket) {
+    let peer_id = ctx.peer_id;
+    let Some((item_id, held, name)) = requested_item(ctx, text) else { return; };
+    let count = text.get_parsed::<i32>("count").unwrap_or(0).min(held);
+    if count <= 0 { return; }
+
+    let removed = change_item(ctx.host, &mut ctx.state.sessions, ctx.db_tx, peer_id, item_id, -count);
+    if removed > 0 {
+        send_console_message(ctx.host, peer_id, &format!("`w{} {}`` trashed.", removed, name));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::player::Player;
+    use crate::database::tile_extra::TileExtra;
+    use crate::database::world::{Tile, World};
+    use crate::network::handler::test_support::TestServer;
+
+    fn server_with(item_id: i32, count: i32) -> TestServer {
+        let mut server = TestServer::new();
+        let mut player = Player::new("tester");
+        player.add_item(item_id, count);
+        player.equipped[0] = item_id;
+        server.join(1, player, World::blank("BAG", 30, 30));
+        server
+    }
+
+    fn held(server: &TestServer, item_id: i32) -> i32 {
+        server.state.sessions.player(1).unwrap().item_count(item_id)
+    }
+
+    #[test]
+    fn change_item_unequips_when_the_last_one_is_gone() {
+        let mut server = server_with(2, 5);
+        let (host, sessions, db_tx) = (&mut server.host, &mut server.state.sessions, &server.db_tx);
+        assert_eq!(change_item(host, sessions, db_tx, 1, 2, 3), 3);
+        assert_eq!(change_item(host, sessions, db_tx, 1, 2, -10), 8);
+        assert_eq!(change_item(host, sessions, db_tx, 1, 2, -1), 0);
+        assert_eq!(server.state.sessions.player(1).unwrap().equipped[0], 0);
+    }
+
+    #[test]
+    fn trash_item_removes_the_requested_count() {
+        let mut server = server_with(2, 10);
+        server.dispatch(1, "action|dialog_return\ndialog_name|trash_item\nitemID|2|\ncount|4\n");
+        assert_eq!(held(&server, 2), 6);
+    }
+
+    #[test]
+    fn drop_item_drops_at_most_what_is_held() {
+        let mut server = server_with(2, 10);
+        server.dispatch(1, "action|dialog_return\ndialog_name|drop_item\nitemID|2|\ncount|15\n");
+        assert_eq!(held(&server, 2), 0);
+        let objects = &server.world("BAG").objects;
+        assert_eq!(objects.len(), 1);
+        assert_eq!((objects[0].item_id, objects[0].count), (2, 10));
+    }
+
+    #[test]
+    fn no_drop_lock_lets_admins_drop() {
+        let mut server = server_with(2, 10);
+        let world = server.state.active_worlds.get_mut("BAG").unwrap();
+        world.tiles[0] = Tile::new(242, 0);
+        world.tiles[0].extra = Some(TileExtra::Lock {
+            flags: crate::game::locks::LOCK_NO_DROP,
+            owner_name: "owner".to_string(),
+            admins: vec!["tester".to_string()],
+            minimum_level: 0,
+        });
+        server.dispatch(1, "action|dialog_return\ndialog_name|drop_item\nitemID|2|\ncount|3\n");
+        assert_eq!(held(&server, 2), 7);
+
+        let world = server.state.active_worlds.get_mut("BAG").unwrap();
+        let Some(TileExtra::Lock { admins, .. }) = world.tiles[0].extra.as_mut() else { unreachable!(); };
+        admins.clear();
+        server.dispatch(1, "action|dialog_return\ndialog_name|drop_item\nitemID|2|\ncount|3\n");
+        assert_eq!(held(&server, 2), 7);
+    }
+}
@@ -1,7 +1,7 @@
 use crate::database::world::{World, TileChangeResult};
 use tracing::info;
 
-pub fn handle_punch_effects(world: &mut World, start_x: i32, start_y: i32, direction: bool, equipped_items: &[i32], punched_block_id: i32) -> Vec<(i32, i32, TileChangeResult)> {
+pub fn handle_punch_effects(world: &mut World, player_name: &str, start_x: i32, start_y: i32, direction: bool, equipped_items: &[i32], punched_block_id: i32) -> Vec<(i32, i32, TileChangeResult)> {
     let mut results = Vec::new();
 
 
@@ -28,7 +28,9 @@ pub fn handle_punch_effects(world: &mut World, start_x: i32, start_y: i32, direc
             if target_x >= 0 && target_x < world.width as i32 {
                 let idx = (target_y * world.width as i32 + target_x) as usize;
                 if idx < world.tiles.len() {
+                    if !world.can_build(idx, player_name) { continue; }
                     let tile = &world.tiles[idx];
+                    if crate::game::locks::lock_kind(tile.fg).is_some() { continue; }
                     let fg = tile.fg as i32;
                     let bg = tile.bg as i32;
 
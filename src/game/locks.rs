@@ -0,0 +1,232 @@
+use std::collections::{HashSet, VecDeque};
+use crate::database::db_thread::DbCommand;
+use crate::database::tile_extra::TileExtra;
+use crate::database::world::World;
+use crate::game::gui::{DialogBuilder, Size};
+use crate::network::handler::{ActionContext, ActionRegistry};
+use crate::network::host::Host;
+use crate::network::packet::{GamePacket, TextPacket, VariantListBuilder};
+use crate::network::server::{broadcast_to_world, send_console_message, send_variant};
+
+pub const LOCK_PUBLIC: u8 = 0x01;
+pub const LOCK_NO_DROP: u8 = 0x02;
+
+const MAX_ADMINS: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockKind {
+    World,
+    Area(usize),
+}
+
+pub fn lock_kind(item_id: u16) -> Option<LockKind> {
+    match item_id {
+        242 | 1796 => Some(LockKind::World),
+        202 => Some(LockKind::Area(10)),
+        204 => Some(LockKind::Area(48)),
+        206 => Some(LockKind::Area(200)),
+        _ => None,
+    }
+}
+
+pub fn allows(lock: &TileExtra, player_name: &str) -> bool {
+    match lock {
+        TileExtra::Lock { flags, owner_name, admins, .. } => {
+            flags & LOCK_PUBLIC != 0
+                || owner_name.eq_ignore_ascii_case(player_name)
+                || admins.iter().any(|a| a.eq_ignore_ascii_case(player_name))
+        }
+        _ => true,
+    }
+}
+
+pub fn is_owner(lock: &TileExtra, player_name: &str) -> bool {
+    matches!(lock, TileExtra::Lock { owner_name, .. } if owner_name.eq_ignore_ascii_case(player_name))
+}
+
+pub fn flood_fill(world: &World, start: usize, size: usize) -> Vec<u32> {
+    let mut area = Vec::with_capacity(size);
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::new();
+    seen.insert(start);
+    queue.push_back(start);
+
+    while let Some(index) = queue.pop_front() {
+        if area.len() >= size { break; }
+        area.push(index as u32);
+
+        let (x, y) = world.tile_pos(index);
+        let (x, y) = (x as i32, y as i32);
+        for (nx, ny) in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+            let Some(next) = world.tile_index(nx, ny) else { continue; };
+            if !seen.insert(next) { continue; }
+
+            let tile = &world.tiles[next];
+            if tile.fg == 6 || lock_kind(tile.fg).is_some() { continue; }
+            if world.area_lock_at(next).is_some() { continue; }
+            queue.push_back(next);
+        }
+    }
+
+    area.sort_unstable();
+    area
+}
+
+pub fn register_handlers(registry: &mut ActionRegistry) {
+    registry.register_dialog("lock_edit", handle_lock_edit);
+}
+
+pub fn build_lock_dialog(world: &World, index: usize) -> Option<String> {
+    let tile = world.tiles.get(index)?;
+    let kind = lock_kind(tile.fg)?;
+    let Some(TileExtra::Lock { flags, owner_name, admins, .. }) = &tile.extra else { return None; };
+    let (x, y) = world.tile_pos(index);
+    let item_name = crate::database::item_config::get_item_config(tile.fg as i32).name;
+
+    let mut dialog = DialogBuilder::new()
+        .default_color("`o")
+        .label_with_icon(Size::Big, &format!("`wEdit {}``", item_name), tile.fg)
+        .embed_data("tilex", x)
+        .embed_data("tiley", y)
+        .textbox(&format!("`wOwner: `o{}``", owner_name))
+        .spacer(Size::Small);
+
+    if admins.is_empty() {
+        dialog = dialog.textbox("Nobody else has access yet.");
+    }
+    for (i, admin) in admins.iter().enumerate() {
+        dialog = dialog.checkbox(&format!("admin_{}", i), admin, true);
+    }
+
+    dialog = dialog
+        .text_input("add_admin", "Add access:", "", 18)
+        .spacer(Size::Small)
+        .checkbox("lock_public", "Allow anyone to build", flags & LOCK_PUBLIC != 0);
+
+    if kind == LockKind::World {
+        dialog = dialog.checkbox("lock_no_drop", "Disable dropping items", flags & LOCK_NO_DROP != 0);
+        dialog = crate::game::world_settings::add_fields(dialog, world);
+    }
+
+    Some(dialog.end_dialog("lock_edit", "Cancel", "OK").build())
+}
+
+pub fn show_lock_editor(host: &mut Host, world: &World, peer_id: u32, player_name: &str, index: usize) -> bool {
+    let Some(dialog) = build_lock_dialog(world, index) else { return false; };
+    if !world.tiles[index].extra.as_ref().is_some_and(|l| is_owner(l, player_name)) {
+        let owner = world.lock_owner(index).unwrap_or("someone");
+        send_console_message(host, peer_id, &format!("`wThis lock is owned by {}.``", owner));
+        return true;
+    }
+
+    let (d_data, d_c) = VariantListBuilder::new()
+        .add_string("OnDialogRequest").add_string(&dialog).build();
+    send_variant(host, peer_id, d_data, d_c, -1, 0);
+    true
+}
+
+fn handle_lock_edit(ctx: &mut ActionContext<'_>, text: &TextPacket) {
+    let peer_id = ctx.peer_id;
+    let (Some(x), Some(y)) = (text.get_parsed::<i32>("tilex"), text.get_parsed::<i32>("tiley")) else { return; };
+    let Some(world_name) = ctx.state.sessions.world(peer_id).map(str::to_string) else { return; };
+    let player_name = ctx.state.sessions.name(peer_id).unwrap_or_default().to_string();
+    let Some(world) = ctx.state.active_worlds.get_mut(&world_name) else { return; };
+    let Some(index) = world.tile_index(x, y) else { return; };
+    let Some(kind) = lock_kind(world.tiles[index].fg) else { return; };
+
+    let Some(TileExtra::Lock { flags, owner_name, admins, .. }) = &mut world.tiles[index].extra else { return; };
+    if !owner_name.eq_ignore_ascii_case(&player_name) {
+        send_console_message(ctx.host, peer_id, "`4Only the owner can edit this lock.``");
+        return;
+    }
+
+    let mut kept: Vec<String> = admins.iter().enumerate()
+        .filter(|(i, _)| text.checkbox(&format!("admin_{}", i)).unwrap_or(true))
+        .map(|(_, a)| a.clone())
+        .collect();
+
+    let new_admin = text.get("add_admin").unwrap_or_default().trim();
+    if !new_admin.is_empty() && !new_admin.eq_ignore_ascii_case(owner_name)
+        && !kept.iter().any(|a| a.eq_ignore_ascii_case(new_admin)) {
+        if kept.len() >= MAX_ADMINS {
+            send_console_message(ctx.host, peer_id, "`4This lock already has the maximum number of admins.``");
+        } else if matches!(crate::database::player::player_exists(new_admin), Ok(true)) {
+            kept.push(new_admin.to_string());
+        } else {
+            send_console_message(ctx.host, peer_id, &format!("`4Player {} does not exist.``", new_admin));
+        }
+    }
+    *admins = kept;
+
+    let mut new_flags = *flags & !(LOCK_PUBLIC | LOCK_NO_DROP);
+    if text.checkbox("lock_public").unwrap_or(false) { new_flags |= LOCK_PUBLIC; }
+    if kind == LockKind::World && text.checkbox("lock_no_drop").unwrap_or(false) { new_flags |= LOCK_NO_DROP; }
+    *flags = new_flags;
+
+    let old_weather = world.settings.weather;
+    if kind == LockKind::World {
+        for error in crate::game::world_settings::apply(world, text) {
+            send_console_message(ctx.host, peer_id, &format!("`4{}``", error));
+        }
+    }
+
+    ctx.db_tx.send(DbCommand::UpdateWorld(world.clone())).ok();
+    if world.settings.weather != old_weather {
+        crate::game::world_settings::broadcast_weather(ctx.host, &ctx.state.sessions, &world_name, world.settings.weather);
+    }
+
+    let update = GamePacket::send_tile_update(x, y).to_bytes_with_raw_data(&world.tile_to_bytes(index));
+    broadcast_to_world(ctx.host, &ctx.state.sessions, &world_name, &update, None, false);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::player::Player;
+    use crate::network::handler::test_support::TestServer;
+
+    fn locked_world() -> (World, usize) {
+        let mut world = World::blank("LOCKED", 30, 30);
+        let index = world.tile_index(10, 20).unwrap();
+        world.tiles[index].fg = 242;
+        world.tiles[index].extra = Some(TileExtra::Lock {
+            flags: 0,
+            owner_name: "owner".to_string(),
+            admins: vec!["helper".to_string(), "friend".to_string()],
+            minimum_level: 0,
+        });
+        (world, index)
+    }
+
+    const LOCK_EDIT: &str = "action|dialog_return\ndialog_name|lock_edit\ntilex|10|\ntiley|20|\nadmin_0|0\nadmin_1|1\nadd_admin|\nlock_public|1\nlock_no_drop|1\nmin_level|5\nno_punch|0\nzombie|0\nmusic|\nweather|3\ndescription|hi\n";
+
+    #[test]
+    fn lock_edit_updates_admins_flags_and_settings() {
+        let mut server = TestServer::new();
+        let (world, index) = locked_world();
+        server.join(1, Player::new("owner"), world);
+
+        server.dispatch(1, LOCK_EDIT);
+
+        let world = server.world("LOCKED");
+        let Some(TileExtra::Lock { flags, admins, minimum_level, .. }) = &world.tiles[index].extra else { panic!("lock extra missing"); };
+        assert_eq!(admins, &vec!["friend".to_string()]);
+        assert_eq!(*flags, LOCK_PUBLIC | LOCK_NO_DROP);
+        assert_eq!(*minimum_level, 5);
+        assert_eq!(world.settings.weather, 3);
+        assert_eq!(world.settings.description, "hi");
+    }
+
+    #[test]
+    fn lock_edit_is_owner_only() {
+        let mut server = TestServer::new();
+        let (world, index) = locked_world();
+        server.join(1, Player::new("helper"), world);
+
+        server.dispatch(1, LOCK_EDIT);
+
+        let Some(TileExtra::Lock { flags, admins, .. }) = &server.world("LOCKED").tiles[index].extra else { panic!("lock extra missing"); };
+        assert_eq!(admins.len(), 2);
+        assert_eq!(*flags, 0);
+    }
+}
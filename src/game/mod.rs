@@ -1,4 +1,20 @@
 pub mod item_effects;
 pub mod gui;
 pub mod gt_mmo;
-pub mod npc;
\ No newline at end of file
+pub mod npc;
+pub mod state;
+pub mod tick;
+pub mod door;
+pub mod locks;
+pub mod farming;
+pub mod drops;
+pub mod inventory;
+pub mod worldgen;
+pub mod healing;
+pub mod world_menu;
+pub mod world_names;
+pub mod world_settings;
+pub mod moderation;
+pub mod history;
+pub mod world_edit;
+pub mod world_transfer;
\ No newline at end of file
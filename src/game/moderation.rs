@@ -0,0 +1,267 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::database::db_thread::DbCommand;
+use crate::database::tile_extra::TileExtra;
+use crate::network::handler::{ActionContext, ActionRegistry};
+use crate::network::packet::{GamePacket, TextPacket, VariantListBuilder};
+use crate::network::server::{broadcast_on_remove, broadcast_to_world, send_console_message, send_world_select_menu};
+
+pub const DEFAULT_BAN_SECS: u64 = 3600;
+pub const MAX_BAN_SECS: u64 = 30 * 86400;
+
+pub fn register_handlers(registry: &mut ActionRegistry) {
+    registry.register_dialog("player_moderate", handle_player_moderate);
+}
+
+pub fn parse_duration(value: &str) -> Option<u64> {
+    let value = value.trim().to_lowercase();
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(split) => value.split_at(split),
+        None => (value.as_str(), "m"),
+    };
+    let amount: u64 = number.parse().ok()?;
+    let secs = match unit {
+        "s" => amount,
+        "m" => amount.checked_mul(60)?,
+        "h" => amount.checked_mul(3600)?,
+        "d" => amount.checked_mul(86400)?,
+        _ => return None,
+    };
+    (secs > 0).then_some(secs.min(MAX_BAN_SECS))
+}
+
+pub fn handle_command(ctx: &mut ActionContext<'_>, command: &str, args: &[&str]) -> bool {
+    let target = args.first().copied().unwrap_or_default();
+    match command {
+        "kick" if !target.is_empty() => {
+            if let Some(target_peer) = target_in_world(ctx, target) { kick(ctx, target_peer); }
+        }
+        "pull" if !target.is_empty() => {
+            if let Some(target_peer) = target_in_world(ctx, target) { pull(ctx, target_peer); }
+        }
+        "ban" if !target.is_empty() => {
+            let duration = match args.get(1) {
+                Some(value) => match parse_duration(value) {
+                    Some(secs) => secs,
+                    None => {
+                        send_console_message(ctx.host, ctx.peer_id, "`4Usage: ``/ban <player> [duration, e.g. 30m, 2h, 1d]");
+                        return true;
+                    }
+                },
+                None => DEFAULT_BAN_SECS,
+            };
+            ban(ctx, target, duration);
+        }
+        "unban" if !target.is_empty() => unban(ctx, target),
+        "unaccess" => unaccess(ctx),
+        "kick" | "pull" | "ban" | "unban" => {
+            send_console_message(ctx.host, ctx.peer_id, &format!("`4Usage: ``/{} <player>", command));
+        }
+        _ => return false,
+    }
+    true
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+fn moderated_world(ctx: &mut ActionContext<'_>) -> Option<(String, String)> {
+    let peer_id = ctx.peer_id;
+    let world_name = ctx.state.sessions.world(peer_id)?.to_string();
+    let actor = ctx.state.sessions.name(peer_id)?.to_string();
+    let world = ctx.state.active_worlds.get(&world_name)?;
+    if !world.has_access(&actor) {
+        send_console_message(ctx.host, peer_id, "`4You need access to this world's lock to do that.``");
+        return None;
+    }
+    Some((world_name, actor))
+}
+
+fn can_target(ctx: &mut ActionContext<'_>, world_name: &str, actor: &str, target: &str) -> bool {
+    let Some(world) = ctx.state.active_worlds.get(world_name) else { return false; };
+    let allowed = !target.eq_ignore_ascii_case(actor)
+        && !world.owner_name.eq_ignore_ascii_case(target)
+        && (!world.has_access(target) || world.owner_name.eq_ignore_ascii_case(actor));
+    if !allowed {
+        send_console_message(ctx.host, ctx.peer_id, &format!("`4You can't do that to {}.``", target));
+    }
+    allowed
+}
+
+fn target_in_world(ctx: &mut ActionContext<'_>, target: &str) -> Option<u32> {
+    let world_name = ctx.state.sessions.world(ctx.peer_id)?.to_string();
+    let found = ctx.state.sessions.peers_in_world(&world_name).into_iter()
+        .find(|&p| ctx.state.sessions.name(p).is_some_and(|n| n.eq_ignore_ascii_case(target)));
+    if found.is_none() {
+        send_console_message(ctx.host, ctx.peer_id, &format!("`4{} is not in this world.``", target));
+    }
+    found
+}
+
+fn announce(ctx: &mut ActionContext<'_>, world_name: &str, message: &str) {
+    for peer_id in ctx.state.sessions.peers_in_world(world_name) {
+        send_console_message(ctx.host, peer_id, message);
+    }
+}
+
+fn move_player(ctx: &mut ActionContext<'_>, world_name: &str, target_peer: u32, (x, y): (f32, f32)) {
+    ctx.state.sessions.entry(target_peer).pos = (x, y);
+    let (v_data, v_c) = VariantListBuilder::new()
+        .add_string("OnSetPos").add_vec2(x, y).build();
+    let pkt = GamePacket::call_function(target_peer as i32, 0);
+    broadcast_to_world(ctx.host, &ctx.state.sessions, world_name, &pkt.to_bytes(&v_data, v_c), None, false);
+}
+
+pub fn kick(ctx: &mut ActionContext<'_>, target_peer: u32) {
+    let Some((world_name, actor)) = moderated_world(ctx) else { return; };
+    let target = ctx.state.sessions.name(target_peer).unwrap_or_default().to_string();
+    if ctx.state.sessions.world(target_peer) != Some(world_name.as_str()) || !can_target(ctx, &world_name, &actor, &target) { return; }
+
+    let Some(world) = ctx.state.active_worlds.get(&world_name) else { return; };
+    let Some(door) = world.main_door() else { return; };
+    let (dx, dy) = world.tile_pos(door);
+    move_player(ctx, &world_name, target_peer, ((dx * 32) as f32, (dy * 32) as f32));
+    announce(ctx, &world_name, &format!("`w{}`` `4kicked`` `w{}``!", actor, target));
+}
+
+pub fn pull(ctx: &mut ActionContext<'_>, target_peer: u32) {
+    let Some((world_name, actor)) = moderated_world(ctx) else { return; };
+    let target = ctx.state.sessions.name(target_peer).unwrap_or_default().to_string();
+    if ctx.state.sessions.world(target_peer) != Some(world_name.as_str()) || !can_target(ctx, &world_name, &actor, &target) { return; }
+
+    let Some(pos) = ctx.state.sessions.pos(ctx.peer_id) else { return; };
+    move_player(ctx, &world_name, target_peer, pos);
+    announce(ctx, &world_name, &format!("`w{}`` pulls `w{}``!", actor, target));
+}
+
+pub fn ban(ctx: &mut ActionContext<'_>, target: &str, duration_secs: u64) {
+    let Some((world_name, actor)) = moderated_world(ctx) else { return; };
+    if !can_target(ctx, &world_name, &actor, target) { return; }
+
+    let target_peer = ctx.state.sessions.peers_in_world(&world_name).into_iter()
+        .find(|&p| ctx.state.sessions.name(p).is_some_and(|n| n.eq_ignore_ascii_case(target)));
+    if target_peer.is_none() && !matches!(crate::database::player::player_exists(target), Ok(true)) {
+        send_console_message(ctx.host, ctx.peer_id, &format!("`4Player {} does not exist.``", target));
+        return;
+    }
+
+    let Some(world) = ctx.state.active_worlds.get_mut(&world_name) else { return; };
+    let now = now();
+    world.prune_bans(now);
+    world.ban(target, now + duration_secs);
+    ctx.db_tx.send(DbCommand::UpdateWorld(world.clone())).ok();
+
+    let length = crate::game::farming::format_duration(duration_secs);
+    announce(ctx, &world_name, &format!("`w{}`` `4world bans`` `w{}`` for {}.", actor, target, length));
+
+    if let Some(target_peer) = target_peer {
+        broadcast_on_remove(ctx.host, target_peer, &ctx.state.sessions);
+        ctx.state.leave_world(target_peer);
+        send_console_message(ctx.host, target_peer, &format!("`4You were banned from {} for {}.``", world_name, length));
+        send_world_select_menu(ctx.host, ctx.state, ctx.config, target_peer);
+    }
+}
+
+pub fn unban(ctx: &mut ActionContext<'_>, target: &str) {
+    let Some((world_name, _)) = moderated_world(ctx) else { return; };
+    let Some(world) = ctx.state.active_worlds.get_mut(&world_name) else { return; };
+    if world.unban(target) {
+        ctx.db_tx.send(DbCommand::UpdateWorld(world.clone())).ok();
+        send_console_message(ctx.host, ctx.peer_id, &format!("`w{}`` is no longer banned from this world.", target));
+    } else {
+        send_console_message(ctx.host, ctx.peer_id, &format!("`4{} is not banned from this world.``", target));
+    }
+}
+
+pub fn unaccess(ctx: &mut ActionContext<'_>) {
+    let peer_id = ctx.peer_id;
+    let Some(world_name) = ctx.state.sessions.world(peer_id).map(str::to_string) else { return; };
+    let Some(actor) = ctx.state.sessions.name(peer_id).map(str::to_string) else { return; };
+    let Some(world) = ctx.state.active_worlds.get_mut(&world_name) else { return; };
+
+    let mut changed = Vec::new();
+    for (index, tile) in world.tiles.iter_mut().enumerate() {
+        let Some(TileExtra::Lock { admins, .. }) = &mut tile.extra else { continue; };
+        let before = admins.len();
+        admins.retain(|a| !a.eq_ignore_ascii_case(&actor));
+        if admins.len() != before { changed.push(index); }
+    }
+
+    if changed.is_empty() {
+        send_console_message(ctx.host, peer_id, "`oYou don't have access to any locks here.``");
+        return;
+    }
+
+    ctx.db_tx.send(DbCommand::UpdateWorld(world.clone())).ok();
+    for &index in &changed {
+        let (x, y) = world.tile_pos(index);
+        let update = GamePacket::send_tile_update(x as i32, y as i32).to_bytes_with_raw_data(&world.tile_to_bytes(index));
+        broadcast_to_world(ctx.host, &ctx.state.sessions, &world_name, &update, None, false);
+    }
+    send_console_message(ctx.host, peer_id, &format!("`oRemoved your access from {} lock(s).``", changed.len()));
+}
+
+pub fn can_moderate(ctx: &ActionContext<'_>, target_peer: u32) -> bool {
+    let (Some(world_name), Some(actor)) = (ctx.state.sessions.world(ctx.peer_id), ctx.state.sessions.name(ctx.peer_id)) else { return false; };
+    ctx.state.sessions.world(target_peer) == Some(world_name)
+        && ctx.state.active_worlds.get(world_name).is_some_and(|w| w.has_access(actor))
+}
+
+fn handle_player_moderate(ctx: &mut ActionContext<'_>, text: &TextPacket) {
+    let Some(target_peer) = text.get_parsed::<u32>("netID") else { return; };
+    match text.get("buttonClicked").unwrap_or_default() {
+        "kick" => kick(ctx, target_peer),
+        "pull" => pull(ctx, target_peer),
+        "ban" => {
+            let Some(target) = ctx.state.sessions.name(target_peer).map(str::to_string) else { return; };
+            ban(ctx, &target, DEFAULT_BAN_SECS);
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::player::Player;
+    use crate::database::world::World;
+    use crate::network::handler::test_support::TestServer;
+
+    fn server_with_lock() -> TestServer {
+        let mut server = TestServer::new();
+        let mut world = World::blank("MOD", 30, 30);
+        world.tiles[0].fg = 242;
+        world.tiles[0].extra = Some(TileExtra::Lock { flags: 0, owner_name: "owner".to_string(), admins: Vec::new(), minimum_level: 0 });
+        server.join(1, Player::new("owner"), world);
+        server.join(5, Player::new("visitor"), World::blank("MOD", 30, 30));
+        server.state.sessions.entry(1).pos = (64.0, 96.0);
+        server
+    }
+
+    #[test]
+    fn pull_moves_the_target_to_the_moderator() {
+        let mut server = server_with_lock();
+        server.dispatch(1, "action|dialog_return\ndialog_name|player_moderate\nnetID|5|\nbuttonClicked|pull\n");
+        assert_eq!(server.state.sessions.pos(5), Some((64.0, 96.0)));
+    }
+
+    #[test]
+    fn player_moderate_requires_world_access() {
+        let mut server = server_with_lock();
+        server.dispatch(5, "action|dialog_return\ndialog_name|player_moderate\nnetID|1|\nbuttonClicked|pull\n");
+        assert_eq!(server.state.sessions.pos(1), Some((64.0, 96.0)));
+    }
+
+    #[test]
+    fn durations_parse() {
+        assert_eq!(parse_duration("30"), Some(1800));
+        assert_eq!(parse_duration("45s"), Some(45));
+        assert_eq!(parse_duration("2H"), Some(7200));
+        assert_eq!(parse_duration("1d"), Some(86400));
+        assert_eq!(parse_duration("999d"), Some(MAX_BAN_SECS));
+        assert_eq!(parse_duration("0m"), None);
+        assert_eq!(parse_duration("10y"), None);
+        assert_eq!(parse_duration("h"), None);
+        assert_eq!(parse_duration("99999999999999999999d"), None);
+    }
+}
@@ -1,4 +1,9 @@
+use std::time::Duration;
+use rand::Rng;
 use serde::{Serialize, Deserialize};
+use crate::game::tick::{TickContext, TickScheduler, WorldTick};
+use crate::network::packet::{GamePacket, VariantListBuilder};
+use crate::network::server::{send_console_message, send_packet};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Npc {
@@ -10,7 +15,6 @@ pub struct Npc {
     pub max_health: i32,
     pub target_x: f32,
     pub state: u32,
-    pub last_jump: std::time::SystemTime,
 }
 
 impl Npc {
@@ -24,7 +28,93 @@ impl Npc {
             max_health: health,
             target_x: x,
             state: 0,
-            last_jump: std::time::SystemTime::now(),
         }
     }
-}
\ No newline at end of file
+}
+
+pub fn register_systems(scheduler: &mut TickScheduler) {
+    scheduler.register_world_system(remove_dead_npcs);
+    scheduler.every(Duration::from_secs(2), move_npcs);
+}
+
+fn remove_dead_npcs(ctx: &mut WorldTick<'_>) {
+    let mut dead_npcs = Vec::new();
+
+    for npc in ctx.world.npcs.iter().filter(|n| n.health <= 0) {
+        dead_npcs.push(npc.net_id);
+
+
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..2 {
+            let off_x = rng.gen_range(-10.0..=10.0);
+            let off_y = rng.gen_range(-10.0..=10.0);
+
+
+            let eff_pkt = GamePacket::particle_effect(-1, npc.x + 16.0 + off_x, npc.y + 16.0 + off_y, 90);
+
+            let eff_bytes = eff_pkt.to_bytes(&[], 0);
+
+            for &p_id in ctx.peers {
+                send_packet(ctx.host, p_id, eff_bytes.clone());
+            }
+        }
+
+
+        for &p_id in ctx.peers {
+            send_console_message(ctx.host, p_id, "`4BOSS DEFEATED!``");
+        }
+    }
+
+    if dead_npcs.is_empty() { return; }
+
+    ctx.world.npcs.retain(|n| !dead_npcs.contains(&n.net_id));
+    for nid in dead_npcs {
+        let msg = format!("netID|{}\n", nid);
+        let pid_str = format!("pId|{}\n", nid);
+
+
+        let (v_data, v_count) = VariantListBuilder::new()
+            .add_string("OnRemove")
+            .add_string(&msg)
+            .add_string(&pid_str)
+            .build();
+
+
+
+        let rem_pkt = GamePacket::call_function(-1, 0);
+        let rem_bytes = rem_pkt.to_bytes(&v_data, v_count);
+
+        for &p_id in ctx.peers {
+            send_packet(ctx.host, p_id, rem_bytes.clone());
+        }
+    }
+}
+
+fn move_npcs(ctx: &mut TickContext<'_>) {
+    let mut rng = rand::thread_rng();
+
+    for (w_name, users_in_world) in ctx.state.sessions.occupied_worlds() {
+        let Some(world) = ctx.state.active_worlds.get_mut(w_name) else { continue; };
+
+        for npc in world.npcs.iter_mut().filter(|n| n.health > 0) {
+            match rng.gen_range(0..4) {
+                0 => npc.x -= 32.0,
+                1 => npc.x += 32.0,
+
+
+                _ => {}
+            }
+
+            npc.x = npc.x.clamp(0.0, world.width as f32 * 32.0);
+            npc.y = npc.y.clamp(0.0, world.height as f32 * 32.0);
+
+            let pkt = GamePacket::state(npc.net_id as i32, npc.x, npc.y);
+
+            let data = pkt.to_bytes(&[], 0);
+            for &p_id in &users_in_world {
+                send_packet(ctx.host, p_id, data.clone());
+            }
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::{HashMap, HashSet};
+use crate::database::player::Player;
+use crate::database::world::World;
+
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub peer_id: u32,
+    pub name: Option<String>,
+    pub player: Option<Player>,
+    pub world: Option<String>,
+    pub pos: (f32, f32),
+    pub hide_players: bool,
+    pub world_edit: crate::game::world_edit::EditSession,
+    pub owned_worlds: Vec<String>,
+}
+
+impl Session {
+    pub fn new(peer_id: u32) -> Self {
+        Self {
+            peer_id,
+            name: None,
+            player: None,
+            world: None,
+            pos: (1000.0, 1000.0),
+            hide_players: false,
+            world_edit: crate::game::world_edit::EditSession::default(),
+            owned_worlds: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Sessions {
+    peers: HashMap<u32, Session>,
+    world_peers: HashMap<String, HashSet<u32>>,
+}
+
+impl Sessions {
+    pub fn get(&self, peer_id: u32) -> Option<&Session> {
+        self.peers.get(&peer_id)
+    }
+
+    pub fn peer_ids(&self) -> Vec<u32> {
+        self.peers.keys().copied().collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+
+    pub fn entry(&mut self, peer_id: u32) -> &mut Session {
+        self.peers.entry(peer_id).or_insert_with(|| Session::new(peer_id))
+    }
+
+    pub fn name(&self, peer_id: u32) -> Option<&str> {
+        self.peers.get(&peer_id).and_then(|s| s.name.as_deref())
+    }
+
+    pub fn set_name(&mut self, peer_id: u32, name: &str) {
+        self.entry(peer_id).name = Some(name.to_string());
+    }
+
+    pub fn player(&self, peer_id: u32) -> Option<&Player> {
+        self.peers.get(&peer_id).and_then(|s| s.player.as_ref())
+    }
+
+    pub fn player_mut(&mut self, peer_id: u32) -> Option<&mut Player> {
+        self.peers.get_mut(&peer_id).and_then(|s| s.player.as_mut())
+    }
+
+    pub fn world(&self, peer_id: u32) -> Option<&str> {
+        self.peers.get(&peer_id).and_then(|s| s.world.as_deref())
+    }
+
+    pub fn pos(&self, peer_id: u32) -> Option<(f32, f32)> {
+        self.peers.get(&peer_id).map(|s| s.pos)
+    }
+
+    pub fn find_by_name(&self, name: &str) -> Option<u32> {
+        self.peers.values()
+            .find(|s| s.name.as_deref().is_some_and(|n| n.eq_ignore_ascii_case(name)))
+            .map(|s| s.peer_id)
+    }
+
+    pub fn peers_in_world(&self, world_name: &str) -> Vec<u32> {
+        self.world_peers.get(world_name).map(|set| set.iter().copied().collect()).unwrap_or_default()
+    }
+
+    pub fn occupied_worlds(&self) -> impl Iterator<Item = (&str, Vec<u32>)> + '_ {
+        self.world_peers.iter().map(|(name, set)| (name.as_str(), set.iter().copied().collect()))
+    }
+
+    pub fn occupied_world_count(&self) -> usize {
+        self.world_peers.len()
+    }
+
+    fn unindex(&mut self, peer_id: u32, world_name: &str) {
+        if let Some(set) = self.world_peers.get_mut(world_name) {
+            set.remove(&peer_id);
+            if set.is_empty() {
+                self.world_peers.remove(world_name);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct GameState {
+    pub sessions: Sessions,
+    pub active_worlds: HashMap<String, World>,
+    pub most_visited: Vec<(String, u32)>,
+    pub edit_queries: crate::game::history::PendingQueries,
+}
+
+impl GameState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn connect(&mut self, peer_id: u32) {
+        self.sessions.peers.insert(peer_id, Session::new(peer_id));
+    }
+
+    pub fn join_world(&mut self, peer_id: u32, world_name: &str) -> Option<String> {
+        let previous = self.leave_world(peer_id);
+        self.sessions.entry(peer_id).world = Some(world_name.to_string());
+        self.sessions.world_peers.entry(world_name.to_string()).or_default().insert(peer_id);
+        previous
+    }
+
+    pub fn leave_world(&mut self, peer_id: u32) -> Option<String> {
+        let previous = self.sessions.peers.get_mut(&peer_id).and_then(|s| s.world.take())?;
+        self.sessions.unindex(peer_id, &previous);
+        Some(previous)
+    }
+
+    pub fn unload_world(&mut self, world_name: &str) -> Option<World> {
+        if !self.sessions.peers_in_world(world_name).is_empty() { return None; }
+        self.active_worlds.remove(world_name)
+    }
+
+    pub fn disconnect(&mut self, peer_id: u32) -> Option<Session> {
+        self.leave_world(peer_id);
+        self.sessions.peers.remove(&peer_id)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(mut peers: Vec<u32>) -> Vec<u32> {
+        peers.sort();
+        peers
+    }
+
+    #[test]
+    fn join_and_switch_worlds_keep_index_in_sync() {
+        let mut state = GameState::new();
+        state.connect(1);
+        state.connect(2);
+
+        assert_eq!(state.join_world(1, "START"), None);
+        assert_eq!(state.join_world(2, "START"), None);
+        assert_eq!(sorted(state.sessions.peers_in_world("START")), vec![1, 2]);
+
+        assert_eq!(state.join_world(1, "BUY"), Some("START".to_string()));
+        assert_eq!(state.sessions.world(1), Some("BUY"));
+        assert_eq!(state.sessions.peers_in_world("START"), vec![2]);
+        assert_eq!(state.sessions.peers_in_world("BUY"), vec![1]);
+
+        assert_eq!(state.leave_world(2), Some("START".to_string()));
+        assert_eq!(state.leave_world(2), None);
+        assert!(state.sessions.peers_in_world("START").is_empty());
+        assert_eq!(state.sessions.occupied_world_count(), 1);
+    }
+
+    #[test]
+    fn disconnect_removes_peer_from_world() {
+        let mut state = GameState::new();
+        state.connect(1);
+        state.sessions.set_name(1, "tester");
+        state.join_world(1, "START");
+
+        let session = state.disconnect(1).unwrap();
+        assert_eq!(session.name.as_deref(), Some("tester"));
+        assert_eq!(session.world, None);
+        assert!(state.sessions.is_empty());
+        assert_eq!(state.sessions.occupied_world_count(), 0);
+        assert!(state.disconnect(1).is_none());
+    }
+
+    #[test]
+    fn occupied_worlds_are_not_unloaded() {
+        let mut state = GameState::new();
+        state.active_worlds.insert("START".to_string(), World::blank("START", 2, 2));
+        state.connect(1);
+        state.join_world(1, "START");
+
+        assert!(state.unload_world("START").is_none());
+        assert!(state.active_worlds.contains_key("START"));
+
+        state.leave_world(1);
+        assert_eq!(state.unload_world("START").map(|w| w.name), Some("START".to_string()));
+        assert!(state.unload_world("START").is_none());
+    }
+}
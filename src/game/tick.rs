@@ -0,0 +1,203 @@
+use std::time::{Duration, Instant};
+use tracing::warn;
+use crate::database::world::World;
+use crate::game::state::GameState;
+use crate::network::host::Host;
+
+const MAX_CATCH_UP_TICKS: u32 = 10;
+
+pub struct TickContext<'a> {
+    pub host: &'a mut Host,
+    pub state: &'a mut GameState,
+}
+
+pub struct WorldTick<'a> {
+    pub host: &'a mut Host,
+    pub world: &'a mut World,
+    pub peers: &'a [u32],
+    pub tick: u64,
+}
+
+type TimerCallback = Box<dyn FnMut(&mut TickContext<'_>)>;
+type WorldSystem = Box<dyn FnMut(&mut WorldTick<'_>)>;
+
+struct Timer {
+    due: u64,
+    interval: u64,
+    callback: TimerCallback,
+}
+
+pub struct TickScheduler {
+    tick_rate: u32,
+    tick_duration: Duration,
+    tick: u64,
+    next_tick: Instant,
+    timers: Vec<Timer>,
+    world_systems: Vec<WorldSystem>,
+    overruns: u64,
+}
+
+impl TickScheduler {
+    pub fn new(tick_rate: u32) -> Self {
+        let tick_rate = tick_rate.max(1);
+        let tick_duration = Duration::from_secs(1) / tick_rate;
+        Self {
+            tick_rate,
+            tick_duration,
+            tick: 0,
+            next_tick: Instant::now() + tick_duration,
+            timers: Vec::new(),
+            world_systems: Vec::new(),
+            overruns: 0,
+        }
+    }
+
+    pub fn ticks_for(&self, duration: Duration) -> u64 {
+        ((duration.as_millis() as u64 * self.tick_rate as u64) / 1000).max(1)
+    }
+
+    pub fn register_world_system(&mut self, system: impl FnMut(&mut WorldTick<'_>) + 'static) {
+        self.world_systems.push(Box::new(system));
+    }
+
+    pub fn every(&mut self, interval: Duration, callback: impl FnMut(&mut TickContext<'_>) + 'static) {
+        let ticks = self.ticks_for(interval);
+        self.timers.push(Timer { due: self.tick + ticks, interval: ticks, callback: Box::new(callback) });
+    }
+
+    pub fn poll(&mut self, host: &mut Host, state: &mut GameState) {
+        let now = Instant::now();
+        if now < self.next_tick { return; }
+
+        let behind = (now - self.next_tick).as_nanos() / self.tick_duration.as_nanos();
+        if behind >= MAX_CATCH_UP_TICKS as u128 {
+            warn!("Tick scheduler is {} ticks behind, skipping ahead", behind);
+            self.next_tick = now;
+        }
+
+        let mut ran = 0;
+        while Instant::now() >= self.next_tick && ran < MAX_CATCH_UP_TICKS {
+            self.run_tick(host, state);
+            self.next_tick += self.tick_duration;
+            ran += 1;
+        }
+    }
+
+    fn run_tick(&mut self, host: &mut Host, state: &mut GameState) {
+        let start = Instant::now();
+        self.tick += 1;
+        let tick = self.tick;
+
+        let mut due = Vec::new();
+        let mut i = 0;
+        while i < self.timers.len() {
+            if self.timers[i].due <= tick {
+                due.push(self.timers.swap_remove(i));
+            } else {
+                i += 1;
+            }
+        }
+
+        for mut timer in due {
+            let mut ctx = TickContext { host, state };
+            (timer.callback)(&mut ctx);
+            timer.due = tick + timer.interval;
+            self.timers.push(timer);
+        }
+
+        let GameState { sessions, active_worlds, .. } = state;
+        for (world_name, peers) in sessions.occupied_worlds() {
+            let Some(world) = active_worlds.get_mut(world_name) else { continue; };
+
+            for system in self.world_systems.iter_mut() {
+                let mut ctx = WorldTick { host, world, peers: &peers, tick };
+                system(&mut ctx);
+            }
+        }
+
+        let elapsed = start.elapsed();
+        if elapsed > self.tick_duration {
+            self.overruns += 1;
+            warn!(
+                "Tick {} took {:.2?} (budget {:.2?}, {} overruns so far)",
+                tick, elapsed, self.tick_duration, self.overruns
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    fn host() -> Host {
+        Host::new("127.0.0.1", 0, 8, 2, false, false, None, None, false, false).unwrap()
+    }
+
+    fn counter(scheduler: &mut TickScheduler, interval: Duration) -> Rc<Cell<u32>> {
+        let count = Rc::new(Cell::new(0));
+        let seen = count.clone();
+        scheduler.every(interval, move |_| seen.set(seen.get() + 1));
+        count
+    }
+
+    #[test]
+    fn interval_timers_repeat() {
+        let (mut host, mut state) = (host(), GameState::new());
+        let mut scheduler = TickScheduler::new(100);
+        assert_eq!(scheduler.ticks_for(Duration::from_millis(30)), 3);
+        assert_eq!(scheduler.ticks_for(Duration::ZERO), 1);
+
+        let every_three = counter(&mut scheduler, Duration::from_millis(30));
+        let every_tick = counter(&mut scheduler, Duration::from_millis(1));
+        for _ in 0..9 {
+            scheduler.run_tick(&mut host, &mut state);
+        }
+        assert_eq!(every_three.get(), 3);
+        assert_eq!(every_tick.get(), 9);
+    }
+
+    #[test]
+    fn poll_catches_up_missed_ticks() {
+        let (mut host, mut state) = (host(), GameState::new());
+        let mut scheduler = TickScheduler::new(10);
+        let ticks = counter(&mut scheduler, Duration::from_millis(100));
+
+        scheduler.poll(&mut host, &mut state);
+        assert_eq!(ticks.get(), 0);
+
+        scheduler.next_tick = Instant::now() - scheduler.tick_duration * 3;
+        scheduler.poll(&mut host, &mut state);
+        assert!((4..=5).contains(&ticks.get()), "ran {} ticks", ticks.get());
+    }
+
+    #[test]
+    fn poll_skips_ahead_when_far_behind() {
+        let (mut host, mut state) = (host(), GameState::new());
+        let mut scheduler = TickScheduler::new(10);
+        let ticks = counter(&mut scheduler, Duration::from_millis(100));
+
+        scheduler.next_tick = Instant::now() - scheduler.tick_duration * (MAX_CATCH_UP_TICKS * 5);
+        scheduler.poll(&mut host, &mut state);
+        assert_eq!(ticks.get(), 1);
+        assert!(scheduler.next_tick > Instant::now());
+    }
+
+    #[test]
+    fn world_systems_run_for_occupied_worlds() {
+        let (mut host, mut state) = (host(), GameState::new());
+        state.active_worlds.insert("START".to_string(), World::blank("START", 2, 2));
+        state.active_worlds.insert("EMPTY".to_string(), World::blank("EMPTY", 2, 2));
+        state.connect(1);
+        state.join_world(1, "START");
+
+        let mut scheduler = TickScheduler::new(10);
+        scheduler.register_world_system(|ctx| ctx.world.visits += ctx.peers.len() as u32);
+        scheduler.run_tick(&mut host, &mut state);
+        scheduler.run_tick(&mut host, &mut state);
+        assert_eq!(state.active_worlds["START"].visits, 2);
+        assert_eq!(state.active_worlds["EMPTY"].visits, 0);
+    }
+}
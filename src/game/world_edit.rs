@@ -0,0 +1,398 @@
+use crate::database::db_thread::DbCommand;
+use crate::database::schematic::Schematic;
+use crate::database::world::World;
+use crate::game::locks::lock_kind;
+use crate::network::handler::ActionContext;
+use crate::network::packet::GamePacket;
+use crate::network::server::{broadcast_to_world, send_console_message};
+
+pub const MAX_REGION_TILES: usize = 10_000;
+pub const MAX_UNDO_STEPS: usize = 10;
+pub const WORLD_RESEND_TILES: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Region {
+    pub x0: u32,
+    pub y0: u32,
+    pub x1: u32,
+    pub y1: u32,
+}
+
+impl Region {
+    pub fn new((ax, ay): (u32, u32), (bx, by): (u32, u32)) -> Self {
+        Self { x0: ax.min(bx), y0: ay.min(by), x1: ax.max(bx), y1: ay.max(by) }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.x1 - self.x0 + 1
+    }
+
+    pub fn height(&self) -> u32 {
+        self.y1 - self.y0 + 1
+    }
+
+    pub fn area(&self) -> usize {
+        self.width() as usize * self.height() as usize
+    }
+
+    pub fn indices(&self, world: &World) -> Vec<usize> {
+        (self.y0..=self.y1)
+            .flat_map(|y| (self.x0..=self.x1).map(move |x| (x, y)))
+            .filter_map(|(x, y)| world.tile_index(x as i32, y as i32))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Layer {
+    Foreground,
+    Background,
+}
+
+impl Layer {
+    pub fn of(item_id: u16) -> Self {
+        if item_id != 0 && crate::database::item_config::get_item_config(item_id as i32).is_background {
+            Layer::Background
+        } else {
+            Layer::Foreground
+        }
+    }
+
+    fn get(self, (fg, bg): (u16, u16)) -> u16 {
+        match self {
+            Layer::Foreground => fg,
+            Layer::Background => bg,
+        }
+    }
+
+    fn set(self, (fg, bg): (u16, u16), item_id: u16) -> (u16, u16) {
+        match self {
+            Layer::Foreground => (item_id, bg),
+            Layer::Background => (fg, item_id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Applied {
+    pub changed: Vec<(usize, (u16, u16))>,
+    pub skipped_locks: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UndoStep {
+    pub world: String,
+    pub tiles: Vec<(usize, (u16, u16))>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EditSession {
+    pub pos1: Option<(u32, u32)>,
+    pub pos2: Option<(u32, u32)>,
+    pub clipboard: Option<Schematic>,
+    pub undo: Vec<UndoStep>,
+}
+
+impl EditSession {
+    pub fn region(&self) -> Option<Region> {
+        Some(Region::new(self.pos1?, self.pos2?))
+    }
+
+    pub fn push_undo(&mut self, step: UndoStep) {
+        self.undo.push(step);
+        if self.undo.len() > MAX_UNDO_STEPS {
+            self.undo.remove(0);
+        }
+    }
+}
+
+fn state_at(world: &World, index: usize) -> (u16, u16) {
+    (world.tiles[index].fg, world.tiles[index].bg)
+}
+
+pub fn fill(world: &World, region: Region, layer: Layer, item_id: u16) -> Vec<(usize, (u16, u16))> {
+    region.indices(world).into_iter()
+        .map(|i| (i, layer.set(state_at(world, i), item_id)))
+        .collect()
+}
+
+pub fn replace(world: &World, region: Region, layer: Layer, from: u16, to: u16) -> Vec<(usize, (u16, u16))> {
+    region.indices(world).into_iter()
+        .filter(|&i| layer.get(state_at(world, i)) == from)
+        .map(|i| (i, layer.set(state_at(world, i), to)))
+        .collect()
+}
+
+pub fn copy(world: &World, region: Region) -> Schematic {
+    let tiles = (region.y0..=region.y1)
+        .flat_map(|y| (region.x0..=region.x1).map(move |x| (x, y)))
+        .map(|(x, y)| world.tile_index(x as i32, y as i32).map_or((0, 0), |i| state_at(world, i)))
+        .collect();
+    Schematic { width: region.width(), height: region.height(), tiles }
+}
+
+pub fn paste(world: &World, schematic: &Schematic, (x, y): (u32, u32)) -> Vec<(usize, (u16, u16))> {
+    (0..schematic.height)
+        .flat_map(|dy| (0..schematic.width).map(move |dx| (dx, dy)))
+        .filter_map(|(dx, dy)| {
+            let index = world.tile_index((x + dx) as i32, (y + dy) as i32)?;
+            Some((index, schematic.get(dx, dy)?))
+        })
+        .collect()
+}
+
+pub fn apply(world: &mut World, targets: &[(usize, (u16, u16))]) -> Applied {
+    let mut applied = Applied::default();
+    for &(index, target) in targets {
+        let Some(tile) = world.tiles.get(index) else { continue; };
+        let before = (tile.fg, tile.bg);
+        if world.restore_tile(index, target) {
+            applied.changed.push((index, before));
+        } else if before != target && (lock_kind(before.0).is_some() || lock_kind(target.0).is_some()) {
+            applied.skipped_locks += 1;
+        }
+    }
+    applied
+}
+
+fn player_tile(ctx: &ActionContext<'_>) -> (u32, u32) {
+    let (px, py) = ctx.state.sessions.pos(ctx.peer_id).unwrap_or_default();
+    (((px + 10.0) / 32.0).max(0.0) as u32, ((py + 15.0) / 32.0).max(0.0) as u32)
+}
+
+fn parse_item(value: &str) -> Option<u16> {
+    if let Ok(id) = value.parse::<u16>() {
+        return (id == 0 || crate::database::item_config::ITEMS.contains_key(&(id as i32))).then_some(id);
+    }
+    let name = value.replace('_', " ");
+    crate::database::item_config::ITEMS.values()
+        .find(|item| item.name.eq_ignore_ascii_case(&name))
+        .map(|item| item.id as u16)
+}
+
+fn commit(ctx: &mut ActionContext<'_>, world_name: &str, actor: &str, targets: &[(usize, (u16, u16))], record_undo: bool) -> (usize, usize) {
+    let Some(world) = ctx.state.active_worlds.get_mut(world_name) else { return (0, 0); };
+    let Applied { changed, skipped_locks } = apply(world, targets);
+    if changed.is_empty() { return (0, skipped_locks); }
+
+    for &(index, before) in &changed {
+        crate::game::history::log_edit(ctx.db_tx, world, actor, index, before);
+    }
+    ctx.db_tx.send(DbCommand::UpdateWorld(world.clone())).ok();
+
+    if changed.len() > WORLD_RESEND_TILES {
+        crate::network::actions::refresh_world(ctx.host, ctx.state, world_name);
+    } else {
+        for &(index, _) in &changed {
+            let (x, y) = world.tile_pos(index);
+            let update = GamePacket::send_tile_update(x as i32, y as i32).to_bytes_with_raw_data(&world.tile_to_bytes(index));
+            broadcast_to_world(ctx.host, &ctx.state.sessions, world_name, &update, None, false);
+        }
+    }
+
+    let count = changed.len();
+    if record_undo {
+        ctx.state.sessions.entry(ctx.peer_id).world_edit.push_undo(UndoStep { world: world_name.to_string(), tiles: changed });
+    }
+    (count, skipped_locks)
+}
+
+fn skipped_note(skipped_locks: usize) -> String {
+    if skipped_locks == 0 { return String::new(); }
+    format!(" `4{} lock tiles were skipped.``", skipped_locks)
+}
+
+pub fn handle_command(ctx: &mut ActionContext<'_>, command: &str, args: &[&str]) -> bool {
+    if !matches!(command, "pos1" | "pos2" | "fill" | "replace" | "copy" | "paste" | "undo" | "schem") {
+        return false;
+    }
+
+    let peer_id = ctx.peer_id;
+    let Some(player) = ctx.state.sessions.player(peer_id) else { return true; };
+    if !player.is_admin() {
+        send_console_message(ctx.host, peer_id, "`4Only admins can use the world edit tools.``");
+        return true;
+    }
+    let actor = player.name.clone();
+    let Some(world_name) = ctx.state.sessions.world(peer_id).map(str::to_string) else { return true; };
+    let Some(world) = ctx.state.active_worlds.get(&world_name) else { return true; };
+    let region = ctx.state.sessions.get(peer_id).and_then(|s| s.world_edit.region());
+
+    let targets = match command {
+        "pos1" | "pos2" => {
+            let (px, py) = player_tile(ctx);
+            let x = args.first().and_then(|v| v.parse().ok()).unwrap_or(px);
+            let y = args.get(1).and_then(|v| v.parse().ok()).unwrap_or(py);
+            if world.tile_index(x as i32, y as i32).is_none() {
+                send_console_message(ctx.host, peer_id, "`4That tile is outside the world.``");
+                return true;
+            }
+            let edit = &mut ctx.state.sessions.entry(peer_id).world_edit;
+            if command == "pos1" { edit.pos1 = Some((x, y)); } else { edit.pos2 = Some((x, y)); }
+            let selection = edit.region().map(|r| format!(" Selection is {}x{} ({} tiles).", r.width(), r.height(), r.area())).unwrap_or_default();
+            send_console_message(ctx.host, peer_id, &format!("`oCorner {} set to {}, {}.{}``", &command[3..], x, y, selection));
+            return true;
+        }
+        "fill" | "replace" | "copy" => {
+            let Some(region) = region else {
+                send_console_message(ctx.host, peer_id, "`4Set both corners first with ``/pos1`4 and ``/pos2`4.``");
+                return true;
+            };
+            if region.area() > MAX_REGION_TILES {
+                send_console_message(ctx.host, peer_id, &format!("`4The selection is too big ({} tiles, max {}).``", region.area(), MAX_REGION_TILES));
+                return true;
+            }
+
+            match command {
+                "copy" => {
+                    let schematic = copy(world, region);
+                    send_console_message(ctx.host, peer_id, &format!("`oCopied {}x{} tiles to your clipboard.``", schematic.width, schematic.height));
+                    ctx.state.sessions.entry(peer_id).world_edit.clipboard = Some(schematic);
+                    return true;
+                }
+                "fill" => {
+                    let Some(item_id) = args.first().and_then(|v| parse_item(v)) else {
+                        send_console_message(ctx.host, peer_id, "`4Usage: ``/fill <item id or name>");
+                        return true;
+                    };
+                    fill(world, region, Layer::of(item_id), item_id)
+                }
+                _ => {
+                    let (Some(from), Some(to)) = (args.first().and_then(|v| parse_item(v)), args.get(1).and_then(|v| parse_item(v))) else {
+                        send_console_message(ctx.host, peer_id, "`4Usage: ``/replace <from item> <to item>");
+                        return true;
+                    };
+                    let layer = Layer::of(if from == 0 { to } else { from });
+                    if to != 0 && Layer::of(to) != layer {
+                        send_console_message(ctx.host, peer_id, "`4Both items must be on the same layer.``");
+                        return true;
+                    }
+                    replace(world, region, layer, from, to)
+                }
+            }
+        }
+        "paste" => {
+            let Some(schematic) = ctx.state.sessions.get(peer_id).and_then(|s| s.world_edit.clipboard.as_ref()) else {
+                send_console_message(ctx.host, peer_id, "`4Your clipboard is empty. Use ``/copy`4 or ``/schem load`4 first.``");
+                return true;
+            };
+            paste(world, schematic, player_tile(ctx))
+        }
+        "undo" => {
+            let edit = &mut ctx.state.sessions.entry(peer_id).world_edit;
+            let Some(position) = edit.undo.iter().rposition(|step| step.world == world_name) else {
+                send_console_message(ctx.host, peer_id, "`4Nothing to undo in this world.``");
+                return true;
+            };
+            let step = edit.undo.remove(position);
+            let (restored, skipped_locks) = commit(ctx, &world_name, &actor, &step.tiles, false);
+            send_console_message(ctx.host, peer_id, &format!("`oUndid the last edit, `w{}`` tiles restored.{}", restored, skipped_note(skipped_locks)));
+            return true;
+        }
+        _ => {
+            handle_schem(ctx, args);
+            return true;
+        }
+    };
+
+    let (changed, skipped_locks) = commit(ctx, &world_name, &actor, &targets, true);
+    send_console_message(ctx.host, peer_id, &format!("`o{} changed `w{}`` tiles. Type ``/undo`o to revert.``{}", command, changed, skipped_note(skipped_locks)));
+    true
+}
+
+fn handle_schem(ctx: &mut ActionContext<'_>, args: &[&str]) {
+    let peer_id = ctx.peer_id;
+    let (Some(&action), Some(&name)) = (args.first(), args.get(1)) else {
+        send_console_message(ctx.host, peer_id, "`4Usage: ``/schem <save|load> <name>");
+        return;
+    };
+
+    match action {
+        "save" => {
+            let Some(schematic) = ctx.state.sessions.get(peer_id).and_then(|s| s.world_edit.clipboard.as_ref()) else {
+                send_console_message(ctx.host, peer_id, "`4Your clipboard is empty.``");
+                return;
+            };
+            match crate::database::schematic::save(name, schematic) {
+                Ok(path) => send_console_message(ctx.host, peer_id, &format!("`oSaved clipboard to `w{}``.", path.display())),
+                Err(e) => send_console_message(ctx.host, peer_id, &format!("`4Could not save schematic: {}``", e)),
+            }
+        }
+        "load" => match crate::database::schematic::load(name) {
+            Ok(schematic) => {
+                send_console_message(ctx.host, peer_id, &format!("`oLoaded {}x{} schematic into your clipboard.``", schematic.width, schematic.height));
+                ctx.state.sessions.entry(peer_id).world_edit.clipboard = Some(schematic);
+            }
+            Err(e) => send_console_message(ctx.host, peer_id, &format!("`4Could not load schematic: {}``", e)),
+        },
+        _ => send_console_message(ctx.host, peer_id, "`4Usage: ``/schem <save|load> <name>"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world() -> World {
+        let mut world = World::blank("TEST", 4, 3);
+        for (i, tile) in world.tiles.iter_mut().enumerate() {
+            tile.fg = if i % 2 == 0 { 2 } else { 0 };
+            tile.bg = 14;
+        }
+        world
+    }
+
+    #[test]
+    fn fill_and_replace_stay_in_region() {
+        let mut world = world();
+        let region = Region::new((2, 1), (1, 0));
+        assert_eq!(region.area(), 4);
+
+        let targets = fill(&world, region, Layer::Foreground, 8);
+        let applied = apply(&mut world, &targets);
+        assert_eq!(applied.changed.len(), 4);
+        assert_eq!(world.tiles[0].fg, 2);
+        assert_eq!(world.tiles[1].fg, 8);
+        assert_eq!(world.tiles[6].fg, 8);
+        assert_eq!(world.tiles[7].fg, 0);
+
+        let targets = replace(&world, Region::new((0, 0), (3, 2)), Layer::Foreground, 2, 0);
+        assert_eq!(targets.len(), 4);
+        assert!(targets.iter().all(|&(_, state)| state == (0, 14)));
+
+        let targets = fill(&world, Region::new((0, 2), (9, 9)), Layer::Background, 0);
+        let undo = apply(&mut world, &targets).changed;
+        assert_eq!(undo.len(), 4);
+        apply(&mut world, &undo);
+        assert!(world.tiles.iter().all(|t| t.bg == 14));
+    }
+
+    #[test]
+    fn copy_and_paste_clip_to_world() {
+        let mut world = world();
+        let schematic = copy(&world, Region::new((0, 0), (1, 1)));
+        assert_eq!(schematic.tiles, vec![(2, 14), (0, 14), (2, 14), (0, 14)]);
+
+        let targets = paste(&world, &schematic, (3, 2));
+        assert_eq!(targets, vec![(11, (2, 14))]);
+        let applied = apply(&mut world, &targets);
+        assert_eq!(applied, Applied { changed: vec![(11, (0, 14))], skipped_locks: 0 });
+        assert_eq!(world.tiles[11].fg, 2);
+    }
+
+    #[test]
+    fn lock_tiles_are_skipped_and_counted() {
+        let mut world = world();
+        world.tiles[1].fg = 242;
+        world.tiles[3].fg = 202;
+
+        let targets = fill(&world, Region::new((0, 0), (3, 0)), Layer::Foreground, 8);
+        let applied = apply(&mut world, &targets);
+        assert_eq!(applied.changed.len(), 2);
+        assert_eq!(applied.skipped_locks, 2);
+        assert_eq!((world.tiles[1].fg, world.tiles[3].fg), (242, 202));
+
+        let targets = fill(&world, Region::new((0, 1), (1, 1)), Layer::Foreground, 242);
+        assert_eq!(apply(&mut world, &targets), Applied { changed: Vec::new(), skipped_locks: 2 });
+    }
+}
@@ -0,0 +1,273 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{error, warn};
+use crate::game::state::{GameState, Sessions};
+use crate::game::tick::TickScheduler;
+
+const TOP_COLOR: u32 = 3529161471;
+const OWNED_COLOR: u32 = 2147418367;
+const RECENT_COLOR: u32 = 3417414143;
+const VISITS_REFRESH: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TopWorldRank {
+    Population,
+    Visits,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WotdRule {
+    Fixed(String),
+    MostVisited,
+    MostPopulated,
+    Daily,
+}
+
+#[derive(Debug, Clone)]
+pub struct WorldMenuConfig {
+    pub top_rank: TopWorldRank,
+    pub top_count: usize,
+    pub wotd: WotdRule,
+}
+
+impl Default for WorldMenuConfig {
+    fn default() -> Self {
+        Self { top_rank: TopWorldRank::Population, top_count: 8, wotd: WotdRule::Daily }
+    }
+}
+
+impl WorldMenuConfig {
+    fn needs_visits(&self) -> bool {
+        self.top_rank == TopWorldRank::Visits || matches!(self.wotd, WotdRule::MostVisited | WotdRule::Daily)
+    }
+
+    pub fn from_env() -> Self {
+        let top_rank = match std::env::var("world_menu_rank").unwrap_or_default().to_lowercase().as_str() {
+            "" | "population" => TopWorldRank::Population,
+            "visits" => TopWorldRank::Visits,
+            other => {
+                warn!("Unknown world_menu_rank '{}', using population", other);
+                TopWorldRank::Population
+            }
+        };
+        Self {
+            top_rank,
+            top_count: std::env::var("world_menu_top_count").ok().and_then(|v| v.parse().ok()).unwrap_or(8),
+            wotd: parse_wotd_rule(&std::env::var("wotd_rule").unwrap_or_default()),
+        }
+    }
+}
+
+pub fn parse_wotd_rule(value: &str) -> WotdRule {
+    match value.trim().to_lowercase().as_str() {
+        "" | "daily" => WotdRule::Daily,
+        "visits" | "most_visited" => WotdRule::MostVisited,
+        "population" | "most_populated" => WotdRule::MostPopulated,
+        other => match other.strip_prefix("fixed:") {
+            Some(name) if !name.is_empty() => WotdRule::Fixed(name.to_uppercase()),
+            _ => {
+                warn!("Unknown wotd_rule '{}', using daily", value);
+                WotdRule::Daily
+            }
+        },
+    }
+}
+
+pub fn register_systems(scheduler: &mut TickScheduler, config: &WorldMenuConfig) {
+    let config = config.clone();
+    scheduler.every(VISITS_REFRESH, move |ctx| refresh_most_visited(ctx.state, &config));
+}
+
+pub fn refresh_most_visited(state: &mut GameState, config: &WorldMenuConfig) {
+    if !config.needs_visits() { return; }
+    match crate::database::world::most_visited_worlds(config.top_count.max(1)) {
+        Ok(worlds) => state.most_visited = worlds,
+        Err(e) => error!("Failed to load most visited worlds: {}", e),
+    }
+}
+
+pub fn load_owned_worlds(sessions: &mut Sessions, peer_id: u32, player_name: &str) {
+    let owned = crate::database::world::worlds_owned_by(player_name).unwrap_or_else(|e| {
+        error!("Failed to load worlds owned by {}: {}", player_name, e);
+        Vec::new()
+    });
+    sessions.entry(peer_id).owned_worlds = owned;
+}
+
+pub fn owner_changed(sessions: &mut Sessions, world_name: &str, previous: &str, current: &str) {
+    if let Some(peer_id) = sessions.find_by_name(previous).filter(|_| !previous.is_empty()) {
+        sessions.entry(peer_id).owned_worlds.retain(|w| w != world_name);
+    }
+    if let Some(peer_id) = sessions.find_by_name(current).filter(|_| !current.is_empty()) {
+        let owned = &mut sessions.entry(peer_id).owned_worlds;
+        if !owned.iter().any(|w| w == world_name) {
+            owned.push(world_name.to_string());
+            owned.sort();
+        }
+    }
+}
+
+pub fn forget_world(sessions: &mut Sessions, world_name: &str) {
+    for peer_id in sessions.peer_ids() {
+        sessions.entry(peer_id).owned_worlds.retain(|w| w != world_name);
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct WorldMenu {
+    pub wotd: Option<String>,
+    pub top: Vec<(String, usize)>,
+    pub owned: Vec<(String, usize)>,
+    pub recent: Vec<(String, usize)>,
+}
+
+impl WorldMenu {
+    pub fn collect(state: &GameState, config: &WorldMenuConfig, peer_id: u32) -> Self {
+        let population = |name: &str| state.sessions.peers_in_world(name).len();
+
+        let mut populated: Vec<(String, usize)> = state.sessions.occupied_worlds()
+            .map(|(name, peers)| (name.to_string(), peers.len()))
+            .collect();
+        populated.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let visited = &state.most_visited;
+
+        let top = match config.top_rank {
+            TopWorldRank::Population => populated.iter().take(config.top_count).cloned().collect(),
+            TopWorldRank::Visits => visited.iter().take(config.top_count).map(|(name, _)| (name.clone(), population(name))).collect(),
+        };
+
+        let wotd = match &config.wotd {
+            WotdRule::Fixed(name) => Some(name.clone()),
+            WotdRule::MostVisited => visited.first().map(|(name, _)| name.clone()),
+            WotdRule::MostPopulated => populated.first().map(|(name, _)| name.clone()),
+            WotdRule::Daily => {
+                let day = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() / 86400;
+                daily_pick(visited, day)
+            }
+        };
+
+        let owned = state.sessions.get(peer_id).map(|s| s.owned_worlds.clone()).unwrap_or_default();
+        let recent = state.sessions.player(peer_id).map(|p| p.recent_worlds.clone()).unwrap_or_default();
+
+        Self {
+            wotd,
+            top,
+            owned: owned.into_iter().map(|name| { let n = population(&name); (name, n) }).collect(),
+            recent: recent.into_iter().map(|name| { let n = population(&name); (name, n) }).collect(),
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut text = String::from("add_filter|\nadd_heading|Top Worlds<ROW2>|\n");
+        if let Some(wotd) = &self.wotd {
+            text.push_str(&format!("add_floater|{}|\u{013B} WOTD|0|0.5|{}\n", wotd, TOP_COLOR));
+        }
+        for (name, players) in &self.top {
+            text.push_str(&floater(name, *players, TOP_COLOR));
+        }
+
+        text.push_str("add_heading|My Worlds<CR>|\n");
+        for (name, players) in &self.owned {
+            text.push_str(&floater(name, *players, OWNED_COLOR));
+        }
+
+        text.push_str("add_heading|Recently Visited Worlds<CR>|\n");
+        for (name, players) in &self.recent {
+            text.push_str(&floater(name, *players, RECENT_COLOR));
+        }
+        text
+    }
+}
+
+fn floater(name: &str, players: usize, color: u32) -> String {
+    let scale = (50 + players.min(10) * 2) as f32 / 100.0;
+    format!("add_floater|{}|{}|{}|{}\n", name, players, scale, color)
+}
+
+pub fn daily_pick(candidates: &[(String, u32)], day: u64) -> Option<String> {
+    if candidates.is_empty() { return None; }
+    Some(candidates[(day % candidates.len() as u64) as usize].0.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wotd_rules_parse() {
+        assert_eq!(parse_wotd_rule(""), WotdRule::Daily);
+        assert_eq!(parse_wotd_rule("Most_Visited"), WotdRule::MostVisited);
+        assert_eq!(parse_wotd_rule("population"), WotdRule::MostPopulated);
+        assert_eq!(parse_wotd_rule("fixed:buy"), WotdRule::Fixed("BUY".to_string()));
+        assert_eq!(parse_wotd_rule("fixed:"), WotdRule::Daily);
+    }
+
+    #[test]
+    fn daily_pick_rotates() {
+        let worlds = vec![("A".to_string(), 9), ("B".to_string(), 5), ("C".to_string(), 1)];
+        assert_eq!(daily_pick(&worlds, 0).as_deref(), Some("A"));
+        assert_eq!(daily_pick(&worlds, 4).as_deref(), Some("B"));
+        assert_eq!(daily_pick(&worlds, 4), daily_pick(&worlds, 4));
+        assert_eq!(daily_pick(&[], 4), None);
+    }
+
+    #[test]
+    fn menu_is_built_from_cached_worlds() {
+        let mut state = GameState::new();
+        state.most_visited = vec![("BUY".to_string(), 90), ("SELL".to_string(), 40), ("OLD".to_string(), 2)];
+        state.connect(1);
+        state.connect(2);
+        state.sessions.set_name(1, "owner");
+        state.sessions.entry(1).player = Some(crate::database::player::Player::new("owner"));
+        state.sessions.entry(1).owned_worlds = vec!["MINE".to_string()];
+        state.join_world(2, "SELL");
+
+        let config = WorldMenuConfig { top_rank: TopWorldRank::Visits, top_count: 2, wotd: WotdRule::MostVisited };
+        let menu = WorldMenu::collect(&state, &config, 1);
+        assert_eq!(menu.wotd.as_deref(), Some("BUY"));
+        assert_eq!(menu.top, vec![("BUY".to_string(), 0), ("SELL".to_string(), 1)]);
+        assert_eq!(menu.owned, vec![("MINE".to_string(), 0)]);
+    }
+
+    #[test]
+    fn owned_worlds_follow_lock_changes() {
+        let mut state = GameState::new();
+        state.connect(1);
+        state.connect(2);
+        state.sessions.set_name(1, "first");
+        state.sessions.set_name(2, "second");
+        state.sessions.entry(1).owned_worlds = vec!["HOME".to_string()];
+        let owned = |state: &GameState, peer_id| state.sessions.get(peer_id).unwrap().owned_worlds.clone();
+
+        owner_changed(&mut state.sessions, "ABC", "", "First");
+        assert_eq!(owned(&state, 1), vec!["ABC", "HOME"]);
+
+        owner_changed(&mut state.sessions, "ABC", "first", "second");
+        assert_eq!(owned(&state, 1), vec!["HOME"]);
+        assert_eq!(owned(&state, 2), vec!["ABC"]);
+
+        forget_world(&mut state.sessions, "ABC");
+        assert!(owned(&state, 2).is_empty());
+    }
+
+    #[test]
+    fn menu_renders_sections_in_order() {
+        let menu = WorldMenu {
+            wotd: Some("BUY".to_string()),
+            top: vec![("START".to_string(), 3)],
+            owned: vec![("MINE".to_string(), 0)],
+            recent: vec![("START".to_string(), 3), ("OLD".to_string(), 0)],
+        };
+        assert_eq!(
+            menu.render(),
+            "add_filter|\nadd_heading|Top Worlds<ROW2>|\n\
+             add_floater|BUY|\u{013B} WOTD|0|0.5|3529161471\n\
+             add_floater|START|3|0.56|3529161471\n\
+             add_heading|My Worlds<CR>|\n\
+             add_floater|MINE|0|0.5|2147418367\n\
+             add_heading|Recently Visited Worlds<CR>|\n\
+             add_floater|START|3|0.56|3417414143\n\
+             add_floater|OLD|0|0.5|3417414143\n"
+        );
+    }
+}
@@ -0,0 +1,72 @@
+pub const MAX_WORLD_NAME_LEN: usize = 24;
+
+pub const RESERVED_WORLD_NAMES: &[&str] = &[
+    "EXIT", "NULL", "NONE", "CON", "PRN", "AUX", "NUL", "ADMIN", "SERVER", "SYSTEM", "GROWTOPIA",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NameError {
+    Empty,
+    TooLong,
+    InvalidCharacters,
+    Reserved,
+    Banned,
+}
+
+impl NameError {
+    pub fn message(&self) -> String {
+        match self {
+            NameError::Empty => "`4Oops!`` You need to type a world name.".to_string(),
+            NameError::TooLong => format!("`4Oops!`` World names can be at most {} characters long.", MAX_WORLD_NAME_LEN),
+            NameError::InvalidCharacters => "`4Oops!`` World names can only contain letters and numbers.".to_string(),
+            NameError::Reserved => "`4Oops!`` That world name is reserved.".to_string(),
+            NameError::Banned => "`4That world is not available.``".to_string(),
+        }
+    }
+}
+
+pub fn normalize(name: &str) -> Result<String, NameError> {
+    let name = name.trim().to_uppercase();
+    if name.is_empty() { return Err(NameError::Empty); }
+    if name.len() > MAX_WORLD_NAME_LEN { return Err(NameError::TooLong); }
+    if !name.bytes().all(|b| b.is_ascii_uppercase() || b.is_ascii_digit()) {
+        return Err(NameError::InvalidCharacters);
+    }
+    if RESERVED_WORLD_NAMES.contains(&name.as_str()) { return Err(NameError::Reserved); }
+    Ok(name)
+}
+
+pub fn validate(name: &str) -> Result<String, NameError> {
+    let name = normalize(name)?;
+    match crate::database::world::is_world_banned(&name) {
+        Ok(false) => Ok(name),
+        Ok(true) => Err(NameError::Banned),
+        Err(e) => {
+            tracing::error!("Failed to check world ban for {}: {}", name, e);
+            Err(NameError::Banned)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_uppercased() {
+        assert_eq!(normalize("start"), Ok("START".to_string()));
+        assert_eq!(normalize(" Buy2 "), Ok("BUY2".to_string()));
+        assert_eq!(normalize(&"A".repeat(MAX_WORLD_NAME_LEN)), Ok("A".repeat(MAX_WORLD_NAME_LEN)));
+    }
+
+    #[test]
+    fn bad_names_are_rejected() {
+        assert_eq!(normalize(""), Err(NameError::Empty));
+        assert_eq!(normalize(&"A".repeat(MAX_WORLD_NAME_LEN + 1)), Err(NameError::TooLong));
+        assert_eq!(normalize("MY WORLD"), Err(NameError::InvalidCharacters));
+        assert_eq!(normalize("A';DROP--"), Err(NameError::InvalidCharacters));
+        assert_eq!(normalize("`4RED"), Err(NameError::InvalidCharacters));
+        assert_eq!(normalize("wörld"), Err(NameError::InvalidCharacters));
+        assert_eq!(normalize("exit"), Err(NameError::Reserved));
+    }
+}
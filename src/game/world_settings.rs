@@ -0,0 +1,165 @@
+use crate::database::tile_extra::TileExtra;
+use crate::database::world::World;
+use crate::game::gui::{DialogBuilder, Size};
+use crate::game::state::Sessions;
+use crate::network::host::Host;
+use crate::network::packet::{TextPacket, VariantListBuilder};
+use crate::network::server::{send_console_message, send_packet, send_variant};
+
+pub const MAX_MINIMUM_LEVEL: u8 = 125;
+pub const MAX_WEATHER: u16 = 255;
+pub const MAX_DESCRIPTION_LEN: usize = 120;
+pub const PUNCH_STATE_FLAGS: i32 = 0x100 | 0x800 | 0x4000;
+
+pub const MUSIC_TRACKS: &[&str] = &[
+    "about_theme", "theme2", "theme3", "theme4", "suspended", "wild_west", "spooky", "jungle",
+];
+
+pub fn add_fields(dialog: DialogBuilder, world: &World) -> DialogBuilder {
+    let settings = &world.settings;
+    dialog
+        .spacer(Size::Small)
+        .textbox("`wWorld Settings``")
+        .text_input("min_level", "Minimum entry level:", &world.minimum_level().to_string(), 3)
+        .checkbox("no_punch", "Punch Jammer (no punching)", settings.no_punch)
+        .checkbox("zombie", "Zombie Jammer (no bosses)", settings.zombie)
+        .textbox(&format!("Music: {}", MUSIC_TRACKS.join(", ")))
+        .text_input("music", "World music:", &settings.music, 16)
+        .text_input("weather", "Weather id:", &settings.weather.to_string(), 3)
+        .text_input("description", "Description:", &settings.description, MAX_DESCRIPTION_LEN as u32)
+}
+
+pub fn apply(world: &mut World, text: &TextPacket) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    match text.get_parsed::<u8>("min_level").filter(|l| *l <= MAX_MINIMUM_LEVEL) {
+        Some(level) => {
+            if let Some(TileExtra::Lock { minimum_level, .. }) = world.world_lock().and_then(|i| world.tiles[i].extra.as_mut()) {
+                *minimum_level = level;
+            }
+        }
+        None => errors.push(format!("Minimum level must be between 0 and {}.", MAX_MINIMUM_LEVEL)),
+    }
+
+    let settings = &mut world.settings;
+    settings.no_punch = text.checkbox("no_punch").unwrap_or(false);
+    settings.zombie = text.checkbox("zombie").unwrap_or(false);
+
+    let music = text.get("music").unwrap_or_default().trim().to_lowercase();
+    if music.is_empty() || MUSIC_TRACKS.contains(&music.as_str()) {
+        settings.music = music;
+    } else {
+        errors.push(format!("Unknown music track {}.", crate::game::gui::escape(&music)));
+    }
+
+    match text.get_parsed::<u16>("weather").filter(|w| *w <= MAX_WEATHER) {
+        Some(weather) => settings.weather = weather,
+        None => errors.push(format!("Weather id must be between 0 and {}.", MAX_WEATHER)),
+    }
+
+    settings.description = crate::game::gui::escape(text.get("description").unwrap_or_default().trim())
+        .chars()
+        .take(MAX_DESCRIPTION_LEN)
+        .collect();
+
+    errors
+}
+
+pub fn broadcast_weather(host: &mut Host, sessions: &Sessions, world_name: &str, weather: u16) {
+    for peer_id in sessions.peers_in_world(world_name) {
+        let (w_data, w_c) = VariantListBuilder::new()
+            .add_string("OnSetCurrentWeather").add_int(weather as i32).build();
+        send_variant(host, peer_id, w_data, w_c, -1, 0);
+    }
+}
+
+pub fn send_on_entry(host: &mut Host, world: &World, peer_id: u32) {
+    let settings = &world.settings;
+    if !settings.description.is_empty() {
+        send_console_message(host, peer_id, &format!("`o{}``", settings.description));
+    }
+    if settings.no_punch {
+        send_console_message(host, peer_id, "`oThis world has a `wPunch Jammer``, only players with access can punch.``");
+    }
+    if settings.zombie {
+        send_console_message(host, peer_id, "`oThis world has a `wZombie Jammer``, bosses can't spawn here.``");
+    }
+    if !settings.music.is_empty() {
+        let message = format!("action|play_music\nfile|audio/mp3/{}.mp3\ndelayMS|0\n", settings.music);
+        let mut packet = vec![3, 0, 0, 0];
+        packet.extend_from_slice(message.as_bytes());
+        packet.push(0);
+        send_packet(host, peer_id, packet);
+    }
+}
+
+pub fn punch_blocked(world: &World, player_name: &str) -> bool {
+    world.settings.no_punch && !world.has_access(player_name)
+}
+
+pub fn level_too_low(world: &World, player_name: &str, level: i32) -> Option<u8> {
+    let required = world.minimum_level();
+    (level < required as i32 && !world.has_access(player_name)).then_some(required)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::world::Tile;
+
+    fn locked_world() -> World {
+        let mut world = World::blank("TEST", 3, 2);
+        world.tiles[0] = Tile::new(242, 0);
+        world.tiles[0].extra = Some(TileExtra::Lock {
+            flags: 0,
+            owner_name: "owner".to_string(),
+            admins: vec!["helper".to_string()],
+            minimum_level: 0,
+        });
+        world
+    }
+
+    #[test]
+    fn dialog_values_are_applied() {
+        let mut world = locked_world();
+        let text = TextPacket::parse("action|dialog_return\nmin_level|10\nno_punch|1\nzombie|1\nmusic|Theme2\nweather|7\ndescription|Welcome|home\n");
+        assert!(apply(&mut world, &text).is_empty());
+        assert_eq!(world.minimum_level(), 10);
+        assert!(world.settings.no_punch);
+        assert!(world.settings.zombie);
+        assert_eq!(world.settings.music, "theme2");
+        assert_eq!(world.settings.weather, 7);
+        assert_eq!(world.settings.description, "Welcomehome");
+    }
+
+    #[test]
+    fn invalid_values_are_kept() {
+        let mut world = locked_world();
+        let text = TextPacket::parse("action|dialog_return\nmin_level|200\nmusic|nope\nweather|x\ndescription|\n");
+        assert_eq!(apply(&mut world, &text).len(), 3);
+        assert_eq!(world.minimum_level(), 0);
+        assert_eq!(world.settings.music, "");
+        assert_eq!(world.settings.weather, crate::database::world::DEFAULT_WEATHER);
+    }
+
+    #[test]
+    fn minimum_level_skips_lock_members() {
+        let mut world = locked_world();
+        let text = TextPacket::parse("action|dialog_return\nmin_level|20\nweather|41\n");
+        apply(&mut world, &text);
+        assert_eq!(level_too_low(&world, "stranger", 19), Some(20));
+        assert_eq!(level_too_low(&world, "stranger", 20), None);
+        assert_eq!(level_too_low(&world, "HELPER", 1), None);
+        assert_eq!(level_too_low(&world, "owner", 1), None);
+    }
+
+    #[test]
+    fn punch_jammer_skips_lock_members() {
+        let mut world = locked_world();
+        assert!(!punch_blocked(&world, "stranger"));
+        world.settings.no_punch = true;
+        assert!(punch_blocked(&world, "stranger"));
+        assert!(!punch_blocked(&world, "helper"));
+        assert!(!punch_blocked(&world, "Owner"));
+    }
+}
@@ -0,0 +1,69 @@
+use std::path::Path;
+use std::sync::mpsc::Sender;
+use crate::database::db_thread::DbCommand;
+use crate::database::world_io::{self, WorldFormat};
+use crate::game::state::GameState;
+use crate::network::handler::ActionContext;
+use crate::network::server::send_console_message;
+
+pub fn export_world(state: &GameState, world_name: &str, path: &Path) -> Result<(), String> {
+    let world_name = crate::game::world_names::normalize(world_name).map_err(|e| e.message())?;
+    let result = match state.active_worlds.get(&world_name) {
+        Some(world) => world_io::export_to_file(world, path),
+        None => match crate::database::world::load_world(&world_name) {
+            Ok(Some(world)) => world_io::export_to_file(&world, path),
+            Ok(None) => return Err(format!("World {} does not exist.", world_name)),
+            Err(e) => Err(e),
+        },
+    };
+    result.map_err(|e| format!("Failed to export {}: {:#}", world_name, e))
+}
+
+pub fn import_world(state: &mut GameState, db_tx: &Sender<DbCommand>, path: &Path, world_name: &str) -> Result<String, String> {
+    let world_name = crate::game::world_names::validate(world_name).map_err(|e| e.message())?;
+    if state.active_worlds.contains_key(&world_name) || !matches!(crate::database::world::load_world(&world_name), Ok(None)) {
+        return Err(format!("World {} already exists.", world_name));
+    }
+
+    let world = world_io::import_from_file(path, &world_name).map_err(|e| format!("Failed to import {}: {:#}", path.display(), e))?;
+    db_tx.send(DbCommand::UpdateWorld(world.clone())).ok();
+    crate::game::world_menu::owner_changed(&mut state.sessions, &world_name, "", &world.owner_name);
+    state.active_worlds.insert(world_name.clone(), world);
+    Ok(world_name)
+}
+
+pub fn handle_command(ctx: &mut ActionContext<'_>, command: &str, args: &[&str]) -> bool {
+    if !matches!(command, "exportworld" | "importworld") {
+        return false;
+    }
+
+    let peer_id = ctx.peer_id;
+    if !ctx.state.sessions.player(peer_id).is_some_and(|p| p.is_admin()) {
+        send_console_message(ctx.host, peer_id, "`4Only admins can import or export worlds.``");
+        return true;
+    }
+
+    if command == "exportworld" {
+        let Some(world_name) = ctx.state.sessions.world(peer_id).map(str::to_string) else { return true; };
+        let Some(format) = WorldFormat::parse(args.first().copied().unwrap_or("json")) else {
+            send_console_message(ctx.host, peer_id, "`4Usage: ``/exportworld [json|schem]");
+            return true;
+        };
+        let Some(path) = world_io::world_file_path(&format!("{}.{}", world_name, format.extension())) else { return true; };
+        match export_world(ctx.state, &world_name, &path) {
+            Ok(()) => send_console_message(ctx.host, peer_id, &format!("`oExported {} to `w{}``.", world_name, path.display())),
+            Err(e) => send_console_message(ctx.host, peer_id, &format!("`4{}``", e)),
+        }
+        return true;
+    }
+
+    let (Some(path), Some(world_name)) = (args.first().and_then(|f| world_io::world_file_path(f)), args.get(1)) else {
+        send_console_message(ctx.host, peer_id, &format!("`4Usage: ``/importworld <file in {}/> <new world name>", world_io::WORLD_FILE_DIR));
+        return true;
+    };
+    match import_world(ctx.state, ctx.db_tx, &path, world_name) {
+        Ok(name) => send_console_message(ctx.host, peer_id, &format!("`oImported `w{}`` from {}.", name, path.display())),
+        Err(e) => send_console_message(ctx.host, peer_id, &format!("`4{}``", e)),
+    }
+    true
+}
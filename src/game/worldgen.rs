@@ -0,0 +1,326 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use tracing::warn;
+use crate::database::tile_extra::TileExtra;
+use crate::database::world::World;
+
+pub const DIRT: u16 = 2;
+pub const LAVA: u16 = 4;
+pub const MAIN_DOOR: u16 = 6;
+pub const BEDROCK: u16 = 8;
+pub const ROCK: u16 = 10;
+pub const CAVE_BACKGROUND: u16 = 14;
+
+pub const MIN_WIDTH: u32 = 20;
+pub const MAX_WIDTH: u32 = 400;
+pub const MIN_HEIGHT: u32 = 20;
+pub const MAX_HEIGHT: u32 = 200;
+
+pub trait WorldGenerator: Sync {
+    fn name(&self) -> &'static str;
+    fn generate(&self, world: &mut World, rng: &mut StdRng);
+}
+
+pub struct Classic;
+pub struct Blank;
+pub struct Island;
+pub struct Cave;
+pub struct Obsidian;
+
+pub static GENERATORS: &[&dyn WorldGenerator] = &[&Classic, &Blank, &Island, &Cave, &Obsidian];
+
+pub fn generator(name: &str) -> Option<&'static dyn WorldGenerator> {
+    GENERATORS.iter().copied().find(|g| g.name().eq_ignore_ascii_case(name))
+}
+
+pub fn build_world(name: &str, width: u32, height: u32, generator: &dyn WorldGenerator, seed: u64) -> World {
+    let mut world = World::blank(name, width.clamp(MIN_WIDTH, MAX_WIDTH), height.clamp(MIN_HEIGHT, MAX_HEIGHT));
+    let mut rng = StdRng::seed_from_u64(seed);
+    generator.generate(&mut world, &mut rng);
+    world
+}
+
+pub struct WorldGenConfig {
+    pub width: u32,
+    pub height: u32,
+    pub prefixes: Vec<(String, &'static dyn WorldGenerator)>,
+}
+
+impl Default for WorldGenConfig {
+    fn default() -> Self {
+        Self { width: 100, height: 60, prefixes: Vec::new() }
+    }
+}
+
+impl WorldGenConfig {
+    pub fn from_env() -> Self {
+        let read = |key: &str, default: u32| std::env::var(key).ok().and_then(|v| v.parse().ok()).unwrap_or(default);
+        let prefixes = std::env::var("world_generator_prefixes").unwrap_or_default();
+        Self {
+            width: read("world_width", 100),
+            height: read("world_height", 60),
+            prefixes: parse_prefixes(&prefixes),
+        }
+    }
+
+    pub fn generator_for(&self, world_name: &str) -> &'static dyn WorldGenerator {
+        self.prefixes.iter()
+            .filter(|(prefix, _)| world_name.to_uppercase().starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(&Classic, |(_, g)| *g)
+    }
+
+    pub fn create_world(&self, world_name: &str) -> World {
+        build_world(world_name, self.width, self.height, self.generator_for(world_name), rand::random())
+    }
+}
+
+fn parse_prefixes(value: &str) -> Vec<(String, &'static dyn WorldGenerator)> {
+    let mut prefixes = Vec::new();
+    for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let Some((prefix, name)) = entry.split_once('=') else {
+            warn!("Ignoring world generator mapping '{}', expected PREFIX=generator", entry);
+            continue;
+        };
+        match generator(name.trim()) {
+            Some(g) => prefixes.push((prefix.trim().to_uppercase(), g)),
+            None => warn!("Ignoring world generator mapping '{}', unknown generator", entry),
+        }
+    }
+    prefixes
+}
+
+fn set_tile(world: &mut World, x: u32, y: u32, fg: u16, bg: u16) {
+    let i = (y * world.width + x) as usize;
+    world.tiles[i].fg = fg;
+    world.tiles[i].bg = bg;
+}
+
+fn place_main_door(world: &mut World, x: u32, y: u32) {
+    set_tile(world, x, y, MAIN_DOOR, world.tiles[(y * world.width + x) as usize].bg);
+    world.tiles[(y * world.width + x) as usize].extra = Some(TileExtra::Door {
+        label: "EXIT".to_string(),
+        destination: String::new(),
+        door_id: String::new(),
+        locked: false,
+    });
+    if y + 1 < world.height {
+        set_tile(world, x, y + 1, BEDROCK, CAVE_BACKGROUND);
+    }
+}
+
+fn layered(world: &mut World, rng: &mut StdRng, block: u16, ore: u16, ore_ratio: (u32, u32), lava_ratio: (u32, u32)) {
+    let (width, height) = (world.width, world.height);
+    let surface = height * 37 / 60;
+    let lava_start = height * 50 / 60;
+    let bedrock_start = height - 6;
+
+    for y in surface..height {
+        for x in 0..width {
+            let fg = if y >= bedrock_start {
+                BEDROCK
+            } else if y >= lava_start {
+                if rng.gen_ratio(lava_ratio.0, lava_ratio.1) { LAVA } else { block }
+            } else if y > surface && rng.gen_ratio(ore_ratio.0, ore_ratio.1) {
+                ore
+            } else {
+                block
+            };
+            set_tile(world, x, y, fg, CAVE_BACKGROUND);
+        }
+    }
+
+    let door_x = rng.gen_range(2..width - 2);
+    place_main_door(world, door_x, surface - 1);
+}
+
+impl WorldGenerator for Classic {
+    fn name(&self) -> &'static str { "classic" }
+
+    fn generate(&self, world: &mut World, rng: &mut StdRng) {
+        layered(world, rng, DIRT, ROCK, (1, 38), (3, 8));
+    }
+}
+
+impl WorldGenerator for Obsidian {
+    fn name(&self) -> &'static str { "obsidian" }
+
+    fn generate(&self, world: &mut World, rng: &mut StdRng) {
+        layered(world, rng, ROCK, LAVA, (1, 12), (3, 4));
+    }
+}
+
+impl WorldGenerator for Blank {
+    fn name(&self) -> &'static str { "blank" }
+
+    fn generate(&self, world: &mut World, rng: &mut StdRng) {
+        let bedrock_start = world.height - 6;
+        for y in bedrock_start..world.height {
+            for x in 0..world.width {
+                set_tile(world, x, y, BEDROCK, CAVE_BACKGROUND);
+            }
+        }
+
+        let door_x = rng.gen_range(2..world.width - 2);
+        place_main_door(world, door_x, bedrock_start - 1);
+    }
+}
+
+impl WorldGenerator for Island {
+    fn name(&self) -> &'static str { "island" }
+
+    fn generate(&self, world: &mut World, rng: &mut StdRng) {
+        let (width, height) = (world.width, world.height);
+        let surface = height * 37 / 60;
+        let center = width / 2;
+        let radius = width / 4;
+
+        for x in center - radius..=center + radius {
+            let dx = x.abs_diff(center);
+            let top = surface + dx * dx * 6 / (radius * radius);
+            let bottom = (surface + 12).saturating_sub(dx * dx * 10 / (radius * radius)).min(height - 2);
+            for y in top..bottom.max(top + 1) {
+                let fg = if y > top && rng.gen_ratio(1, 20) { ROCK } else { DIRT };
+                set_tile(world, x, y, fg, CAVE_BACKGROUND);
+            }
+        }
+
+        for y in height - 2..height {
+            for x in 0..width {
+                set_tile(world, x, y, BEDROCK, 0);
+            }
+        }
+
+        let door_x = rng.gen_range(center - radius / 2..=center + radius / 2);
+        let dx = door_x.abs_diff(center);
+        place_main_door(world, door_x, surface + dx * dx * 6 / (radius * radius) - 1);
+    }
+}
+
+impl WorldGenerator for Cave {
+    fn name(&self) -> &'static str { "cave" }
+
+    fn generate(&self, world: &mut World, rng: &mut StdRng) {
+        let (width, height) = (world.width, world.height);
+        let top = 2;
+        let bedrock_start = height - 6;
+
+        let mut solid: Vec<bool> = (0..width * height).map(|_| rng.gen_ratio(11, 20)).collect();
+        let is_solid = |solid: &[bool], x: i64, y: i64| {
+            x < 0 || y < top as i64 || x >= width as i64 || y >= bedrock_start as i64 || solid[(y as u32 * width + x as u32) as usize]
+        };
+        for _ in 0..4 {
+            let mut next = solid.clone();
+            for y in top..bedrock_start {
+                for x in 0..width {
+                    let walls = (-1..=1i64)
+                        .flat_map(|dy| (-1..=1i64).map(move |dx| (dx, dy)))
+                        .filter(|&(dx, dy)| (dx, dy) != (0, 0) && is_solid(&solid, x as i64 + dx, y as i64 + dy))
+                        .count();
+                    next[(y * width + x) as usize] = walls >= 5;
+                }
+            }
+            solid = next;
+        }
+
+        for y in top..height {
+            for x in 0..width {
+                let fg = if y >= bedrock_start {
+                    BEDROCK
+                } else if solid[(y * width + x) as usize] {
+                    if rng.gen_ratio(1, 10) { ROCK } else { DIRT }
+                } else {
+                    0
+                };
+                set_tile(world, x, y, fg, CAVE_BACKGROUND);
+            }
+        }
+
+        let door_x = rng.gen_range(2..width - 2);
+        let door_y = (top..bedrock_start - 1)
+            .find(|&y| world.tiles[(y * width + door_x) as usize].fg == 0)
+            .unwrap_or(top);
+        set_tile(world, door_x, door_y, 0, CAVE_BACKGROUND);
+        place_main_door(world, door_x, door_y);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doors(world: &World) -> Vec<usize> {
+        world.tiles.iter().enumerate().filter(|(_, t)| t.fg == MAIN_DOOR).map(|(i, _)| i).collect()
+    }
+
+    fn layout(world: &World) -> Vec<(u16, u16)> {
+        world.tiles.iter().map(|t| (t.fg, t.bg)).collect()
+    }
+
+    #[test]
+    fn same_seed_same_layout() {
+        for g in GENERATORS {
+            let a = build_world("A", 60, 40, *g, 1234);
+            let b = build_world("A", 60, 40, *g, 1234);
+            assert_eq!(layout(&a), layout(&b), "{}", g.name());
+        }
+        let a = build_world("A", 60, 40, &Cave, 1);
+        let b = build_world("A", 60, 40, &Cave, 2);
+        assert_ne!(layout(&a), layout(&b));
+    }
+
+    #[test]
+    fn every_generator_has_one_door_on_bedrock() {
+        for g in GENERATORS {
+            for seed in 0..20 {
+                let world = build_world("TEST", 80, 50, *g, seed);
+                assert_eq!(world.tiles.len(), 80 * 50);
+                let doors = doors(&world);
+                assert_eq!(doors.len(), 1, "{} seed {}", g.name(), seed);
+                assert_eq!(world.tiles[doors[0] + 80].fg, BEDROCK, "{} seed {}", g.name(), seed);
+                assert!(world.tiles[(49 * 80)..].iter().all(|t| t.fg == BEDROCK), "{} seed {}", g.name(), seed);
+            }
+        }
+    }
+
+    #[test]
+    fn blank_layout_is_exact() {
+        let world = build_world("EMPTY", 30, 20, &Blank, 7);
+        let door = doors(&world)[0];
+        assert_eq!(door / 30, 13);
+        for (i, tile) in world.tiles.iter().enumerate() {
+            let expected = if i == door { MAIN_DOOR } else if i / 30 >= 14 { BEDROCK } else { 0 };
+            assert_eq!(tile.fg, expected, "tile {}", i);
+        }
+    }
+
+    #[test]
+    fn classic_keeps_the_original_layers() {
+        let world = build_world("START", 100, 60, &Classic, 99);
+        let door = doors(&world)[0];
+        assert_eq!(door / 100, 36);
+        assert!(world.tiles[..36 * 100].iter().enumerate().all(|(i, t)| t.fg == 0 || i == door));
+        assert!(world.tiles[37 * 100..].iter().all(|t| t.bg == CAVE_BACKGROUND));
+        assert!(world.tiles[54 * 100..].iter().all(|t| t.fg == BEDROCK));
+    }
+
+    #[test]
+    fn sizes_are_clamped() {
+        let world = build_world("TINY", 1, 100_000, &Classic, 0);
+        assert_eq!((world.width, world.height), (MIN_WIDTH, MAX_HEIGHT));
+        assert_eq!(world.tiles.len(), (MIN_WIDTH * MAX_HEIGHT) as usize);
+    }
+
+    #[test]
+    fn prefixes_pick_generators() {
+        let config = WorldGenConfig {
+            prefixes: parse_prefixes("CAVE=cave, CAVERN=island, BAD, X=nope"),
+            ..WorldGenConfig::default()
+        };
+        assert_eq!(config.prefixes.len(), 2);
+        assert_eq!(config.generator_for("cave1").name(), "cave");
+        assert_eq!(config.generator_for("CAVERNS").name(), "island");
+        assert_eq!(config.generator_for("START").name(), "classic");
+        assert_eq!(generator("OBSIDIAN").map(|g| g.name()), Some("obsidian"));
+    }
+}
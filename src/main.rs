@@ -83,6 +83,26 @@ async fn main() {
         std::thread::sleep(std::time::Duration::from_secs(10));
         return;
     }
+    if let Err(e) = database::tile_history::init_db() {
+        error!("Failed to initialize tile history: {}", e);
+        std::thread::sleep(std::time::Duration::from_secs(10));
+        return;
+    }
+
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(result) = database::world_io::run_cli(&args) {
+        match result {
+            Ok(message) => info!("{}", message),
+            Err(e) => {
+                error!("{:#}", e);
+                drop(_general_guard);
+                drop(_usage_guard);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
 
 
     info!("Initializing crypto provider...");
@@ -103,14 +123,24 @@ async fn main() {
     });
 
 
+    let (stopped_tx, stopped_rx) = tokio::sync::oneshot::channel::<()>();
     let db_tx_clone = db_tx.clone();
     thread::spawn(move || {
-        if let Err(e) = network::server::start_enet_server(cmd_rx, db_tx_clone) {
-            eprintln!("ENet server crashed: {}", e);
+        match network::server::start_enet_server(cmd_rx, db_tx_clone) {
+            Ok(()) => { stopped_tx.send(()).ok(); }
+            Err(e) => eprintln!("ENet server crashed: {}", e),
         }
     });
 
 
+    let signal_tx = cmd_tx.clone();
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        info!("Shutdown signal received");
+        signal_tx.send(crate::network::server::ServerCommand::Shutdown).ok();
+    });
+
+
     thread::spawn(move || {
         use std::io::{self, Write};
         let stdin = io::stdin();
@@ -148,12 +178,74 @@ async fn main() {
                         println!("Sent spawnboss command for {} with hp {}", world_name, health);
                         cmd_tx.send(crate::network::server::ServerCommand::SpawnBoss { world_name, health }).ok();
                     }
+                    "genworld" if parts.len() >= 3 => {
+                        let world_name = parts[1].to_string();
+                        let generator = parts[2].to_string();
+                        let width = parts.get(3).and_then(|v| v.parse().ok());
+                        let height = parts.get(4).and_then(|v| v.parse().ok());
+                        let seed = parts.get(5).and_then(|v| v.parse().ok());
+                        println!("Sent genworld command for {} using {}", world_name, generator);
+                        cmd_tx.send(crate::network::server::ServerCommand::GenerateWorld { world_name, generator, width, height, seed }).ok();
+                    }
+                    "banworld" | "unbanworld" if parts.len() >= 2 => {
+                        let world_name = parts[1].to_string();
+                        let banned = parts[0].eq_ignore_ascii_case("banworld");
+                        println!("Sent {} command for {}", parts[0].to_lowercase(), world_name);
+                        cmd_tx.send(crate::network::server::ServerCommand::BanWorld { world_name, banned }).ok();
+                    }
+                    "nukeworld" if parts.len() >= 2 => {
+                        let world_name = parts[1].to_string();
+                        println!("Sent nukeworld command for {}", world_name);
+                        cmd_tx.send(crate::network::server::ServerCommand::NukeWorld { world_name }).ok();
+                    }
+                    "rollback" if parts.len() >= 4 => {
+                        let player_name = parts[1].to_string();
+                        let world_name = parts[2].to_string();
+                        let minutes: u64 = parts[3].parse().unwrap_or(60);
+                        println!("Sent rollback command for {} in {}", player_name, world_name);
+                        cmd_tx.send(crate::network::server::ServerCommand::Rollback { player_name, world_name, minutes }).ok();
+                    }
+                    "role" if parts.len() >= 3 => {
+                        let player_name = parts[1].to_string();
+                        let role: i32 = parts[2].parse().unwrap_or(1);
+                        println!("Sent role command to {}", player_name);
+                        cmd_tx.send(crate::network::server::ServerCommand::SetRole { player_name, role }).ok();
+                    }
+                    "exportworld" if parts.len() >= 3 => {
+                        let world_name = parts[1].to_string();
+                        let path = parts[2].to_string();
+                        println!("Sent exportworld command for {}", world_name);
+                        cmd_tx.send(crate::network::server::ServerCommand::ExportWorld { world_name, path }).ok();
+                    }
+                    "importworld" if parts.len() >= 3 => {
+                        let path = parts[1].to_string();
+                        let world_name = parts[2].to_string();
+                        println!("Sent importworld command for {}", world_name);
+                        cmd_tx.send(crate::network::server::ServerCommand::ImportWorld { path, world_name }).ok();
+                    }
+                    "status" => {
+                        cmd_tx.send(crate::network::server::ServerCommand::Status).ok();
+                    }
+                    "shutdown" => {
+                        println!("Shutting down server...");
+                        cmd_tx.send(crate::network::server::ServerCommand::Shutdown).ok();
+                    }
                     "help" => {
                         println!("Dev Console Commands:");
                         println!("  give <name> <id> <amount> - Give item to player");
                         println!("  level <name> <level>      - Set player level");
                         println!("  xp <name> <amount>        - Give XP to player");
                         println!("  spawnboss <world> <hp>    - Spawn NPC Boss");
+                        println!("  genworld <world> <generator> [w] [h] [seed] - Create a world (classic, blank, island, cave, obsidian)");
+                        println!("  banworld <world>          - Ban a world name and kick everyone inside");
+                        println!("  unbanworld <world>        - Lift a world ban");
+                        println!("  nukeworld <world>         - Kick everyone and delete the world");
+                        println!("  rollback <name> <world> <minutes> - Revert a player's edits in a world");
+                        println!("  role <name> <role>        - Set player role ({} = admin)", crate::database::player::ROLE_ADMIN);
+                        println!("  exportworld <world> <file> - Export a world to a .json or .gtschem file");
+                        println!("  importworld <file> <world> - Import a .json or .gtschem file as a new world");
+                        println!("  status                    - Show online peers and world counts");
+                        println!("  shutdown                  - Save everything and stop the server");
                         println!("  help                      - Show this help");
                     }
                     _ => {
@@ -208,14 +300,47 @@ async fn main() {
     };
 
 
+    let handle = axum_server::Handle::new();
+    let shutdown_handle = handle.clone();
+    tokio::spawn(async move {
+        if stopped_rx.await.is_ok() {
+            shutdown_handle.graceful_shutdown(Some(std::time::Duration::from_secs(5)));
+        }
+    });
+
+
     let addr = SocketAddr::from(([0, 0, 0, 0], 443));
     info!("Binding HTTPS on 0.0.0.0:443");
 
     if let Err(e) = axum_server::bind_rustls(addr, tls)
+        .handle(handle)
         .serve(app.into_make_service_with_connect_info::<SocketAddr>())
         .await
     {
         error!("HTTPS server error: {}", e);
         std::thread::sleep(std::time::Duration::from_secs(10));
     }
+    info!("Server stopped.");
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c().await.ok();
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => { signal.recv().await; }
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
 }
\ No newline at end of file
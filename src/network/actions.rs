@@ -0,0 +1,379 @@
+use tracing::{info, warn, error};
+use crate::database::{player, world};
+use std::sync::mpsc::Sender;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::database::db_thread::DbCommand;
+use crate::game::state::GameState;
+use crate::network::host::Host;
+use crate::network::handler::{ActionContext, ActionRegistry};
+use crate::network::packet::{GamePacket, TextPacket, VariantListBuilder};
+use crate::network::server::{
+    broadcast_on_remove, broadcast_on_set_clothing, calculate_punch_id, format_spawn_avatar, send_console_message,
+    send_inventory, send_on_set_clothing, send_packet, send_set_character_state, send_variant, send_world_select_menu,
+    ServerConfig,
+};
+
+pub fn register_handlers(registry: &mut ActionRegistry) {
+    registry.register_action("protocol", handle_protocol);
+    registry.register_action("tankIDName", handle_tank_id_name);
+    registry.register_action("refresh_item_data", handle_refresh_item_data);
+    registry.register_action("setSkin", handle_set_skin);
+    registry.register_action("enter_game", handle_enter_game);
+    registry.register_action("join_request", handle_join_request);
+    registry.register_action("quit_to_exit", handle_quit);
+    registry.register_action("quit", handle_quit);
+
+    registry.register_dialog("setSkin", handle_set_skin);
+}
+
+fn handle_protocol(ctx: &mut ActionContext<'_>, text: &TextPacket) {
+    let peer_id = ctx.peer_id;
+    let name = ctx.state.sessions.name(peer_id).map(str::to_string)
+        .or_else(|| text.get("tankIDName").filter(|s| !s.is_empty()).map(str::to_string))
+        .or_else(|| text.get("requestedName").filter(|s| !s.is_empty()).map(str::to_string))
+        .unwrap_or_else(|| "GrowtopiaUser".to_string());
+
+    ctx.state.sessions.set_name(peer_id, &name);
+
+
+    let (id_data, id_count) = VariantListBuilder::new()
+        .add_string("SetHasGrowID").add_int(1).add_string(&name).add_string("").build();
+    send_variant(ctx.host, peer_id, id_data, id_count, -1, 0);
+
+
+    let redirect_str = format!("{}|0|{}", ctx.config.gs_ip, ctx.config.gs_token);
+    let (red_data, red_count) = VariantListBuilder::new()
+        .add_string("OnSendToServer").add_int(ctx.config.gs_port as i32).add_int(8172597).add_int(12345)
+        .add_string(&redirect_str).add_int(1).add_string(&name).build();
+    send_variant(ctx.host, peer_id, red_data, red_count, -1, 0);
+
+    ctx.host.disconnect_later_peer(peer_id, 0).ok();
+}
+
+fn handle_tank_id_name(ctx: &mut ActionContext<'_>, text: &TextPacket) {
+    let peer_id = ctx.peer_id;
+    let player_name = text.get("tankIDName").filter(|s| !s.is_empty()).map(str::to_string)
+        .or_else(|| text.get("requestedName").filter(|s| !s.is_empty()).map(str::to_string))
+        .or_else(|| ctx.state.sessions.name(peer_id).map(str::to_string))
+        .unwrap_or_else(|| "Unknown".to_string());
+
+
+    if let Some(old_id) = ctx.state.sessions.find_by_name(&player_name)
+        && old_id != peer_id {
+        warn!("Kicking duplicate session {} (Peer {})", player_name, old_id);
+        broadcast_on_remove(ctx.host, old_id, &ctx.state.sessions);
+        send_console_message(ctx.host, old_id, "`4Logged in from another location.``");
+        ctx.host.disconnect_now_peer(old_id, 0).ok();
+
+        ctx.state.disconnect(old_id);
+    }
+
+    ctx.state.sessions.set_name(peer_id, &player_name);
+
+    let mut current_player = match player::load_player(&player_name) {
+        Ok(Some(p)) => p,
+        _ => {
+            let p = player::Player::new(&player_name);
+            ctx.db_tx.send(DbCommand::UpdatePlayer(p.clone())).ok();
+            p
+        }
+    };
+
+
+    let mut changed = false;
+    if !current_player.slots.iter().any(|s| s.item_id == 18) {
+        current_player.slots.push(player::InventorySlot { item_id: 18, count: 1 });
+        changed = true;
+    }
+    if !current_player.slots.iter().any(|s| s.item_id == 32) {
+        current_player.slots.push(player::InventorySlot { item_id: 32, count: 1 });
+        changed = true;
+    }
+    if changed { ctx.db_tx.send(DbCommand::UpdatePlayer(current_player.clone())).ok(); }
+
+    ctx.state.sessions.entry(peer_id).player = Some(current_player.clone());
+    crate::game::world_menu::load_owned_worlds(&mut ctx.state.sessions, peer_id, &player_name);
+    info!("Player {} logged in.", current_player.name);
+
+
+
+    let (ftue, c) = VariantListBuilder::new()
+        .add_string("OnFtueButtonDataSet").add_int(0).add_int(0).add_int(0)
+        .add_string("||0|||-1").add_string("").add_string("1|1").build();
+    send_variant(ctx.host, peer_id, ftue, c, -1, 0);
+
+
+    let (hid, c) = VariantListBuilder::new()
+        .add_string("SetHasGrowID").add_int(1).add_string(&current_player.name).add_string("").build();
+    send_variant(ctx.host, peer_id, hid, c, -1, 0);
+
+
+    let (gdpr, c) = VariantListBuilder::new()
+        .add_string("OnOverrideGDPRFromServer").add_int(18).add_int(1).add_int(0).add_int(1).build();
+    send_variant(ctx.host, peer_id, gdpr, c, -1, 0);
+
+
+    let (skin, c) = VariantListBuilder::new()
+        .add_string("OnSetRoleSkinsAndTitles").add_string("000000").add_string("000000").build();
+    send_variant(ctx.host, peer_id, skin, c, -1, 0);
+
+
+    let (logon, c) = VariantListBuilder::new()
+        .add_string("OnSuperMainStartAcceptLogonHrdxs47254722215a")
+        .add_uint(2816436900)
+        .add_string(&std::env::var("webserver_adress").unwrap_or("chaosautomations.com".to_string()))
+        .add_string("cache/")
+        .add_string("cc.cz.madkite.freedom org.aqua.gg idv.aqua.bulldog com.cih.gamecih2 com.cih.gamecih com.cih.game_cih cn.maocai.gamekiller com.gmd.speedtime org.dax.attack com.x0.strai.frep com.x0.strai.free org.cheatengine.cegui org.sbtools.gamehack com.skgames.traffikrider org.sbtoods.gamehaca com.skype.ralder org.cheatengine.cegui.xx.multi1458919170111 com.prohiro.macro me.autotouch.autotouch com.cygery.repetitouch.free com.cygery.repetitouch.pro com.proziro.zacro com.slash.gamebuster")
+        .add_string("proto=225|choosemusic=audio/mp3/about_theme.mp3|active_holiday=0|wing_week_day=0|ubi_week_day=0|server_tick=33784663|game_theme=|clash_active=1|drop_lavacheck_faster=1|isPayingUser=1|usingStoreNavigation=1|enableInventoryTab=1|bigBackpack=1|seed_diary_hash=3701384193|m_clientBits=|eventButtons={\"EventButtonData\":[{\"active\":true,\"buttonAction\":\"eventmenu\",\"buttonState\":0,\"buttonTemplate\":\"BaseEventButton\",\"counter\":0,\"counterMax\":0,\"itemIdIcon\":6828,\"name\":\"ClashEventButton\",\"notification\":0,\"order\":9,\"rcssClass\":\"clash-event\",\"text\":\"Nah\"}]}")
+        .build();
+    send_variant(ctx.host, peer_id, logon, c, -1, 0);
+
+
+    let (eb, c) = VariantListBuilder::new()
+        .add_string("OnEventButtonDataSet").add_string("ClashEventButton").add_int(1)
+        .add_string("{\"active\":true,\"buttonAction\":\"eventmenu\",\"buttonState\":0,\"buttonTemplate\":\"BaseEventButton\",\"counter\":0,\"counterMax\":0,\"itemIdIcon\":6828,\"name\":\"ClashEventButton\",\"notification\":0,\"order\":9,\"rcssClass\":\"clash-event\",\"text\":\"Claim!\"}")
+        .build();
+    send_variant(ctx.host, peer_id, eb, c, -1, 0);
+}
+
+fn handle_refresh_item_data(ctx: &mut ActionContext<'_>, _text: &TextPacket) {
+    let peer_id = ctx.peer_id;
+    send_console_message(ctx.host, peer_id, "One moment, updating item data...");
+    let items_pkt = GamePacket::send_item_database();
+    send_packet(ctx.host, peer_id, items_pkt.to_bytes_with_raw_data(&ctx.config.items_dat));
+}
+
+fn handle_set_skin(ctx: &mut ActionContext<'_>, text: &TextPacket) {
+    let peer_id = ctx.peer_id;
+    if let Some(color_str) = text.get("color")
+        && let Ok(color) = color_str.parse::<u32>()
+        && let Some(player) = ctx.state.sessions.player_mut(peer_id) {
+        player.skin_color = color;
+        ctx.db_tx.send(DbCommand::UpdatePlayer(player.clone())).ok();
+
+
+        broadcast_on_set_clothing(ctx.host, &ctx.state.sessions, peer_id);
+    }
+}
+
+fn handle_enter_game(ctx: &mut ActionContext<'_>, _text: &TextPacket) {
+    let peer_id = ctx.peer_id;
+    if ctx.state.sessions.world(peer_id).is_none() {
+        let name = ctx.state.sessions.name(peer_id).unwrap_or("Unknown").to_string();
+
+
+        let (ftue, c) = VariantListBuilder::new()
+            .add_string("OnFtueButtonDataSet").add_int(0).add_int(0).add_int(0)
+            .add_string("||0|||-1").add_string("").add_string("1|1").build();
+        send_variant(ctx.host, peer_id, ftue, c, -1, 0);
+
+
+        send_console_message(ctx.host, peer_id, &format!("Welcome back, `w{}````.", name));
+
+
+        let (date, c) = VariantListBuilder::new()
+            .add_string("OnTodaysDate").add_int(2).add_int(3).add_int(0).add_int(0).build();
+        send_variant(ctx.host, peer_id, date, c, -1, 0);
+
+
+        send_world_select_menu(ctx.host, ctx.state, ctx.config, peer_id);
+
+
+        let gazette_text = crate::game::gui::build_gazette();
+        let (gaz, c) = VariantListBuilder::new()
+            .add_string("OnDialogRequest").add_string(&gazette_text).build();
+        send_variant(ctx.host, peer_id, gaz, c, -1, 0);
+
+
+        let ping = GamePacket::ping_request();
+        send_packet(ctx.host, peer_id, ping.to_bytes(&[], 0));
+    }
+}
+
+fn handle_join_request(ctx: &mut ActionContext<'_>, text: &TextPacket) {
+    let world_name = text.get("name").filter(|s| !s.is_empty()).unwrap_or("START").to_string();
+    enter_world(ctx.host, ctx.state, ctx.db_tx, ctx.config, ctx.peer_id, &world_name, None);
+}
+
+pub fn enter_world(
+    host: &mut Host,
+    state: &mut GameState,
+    db_tx: &Sender<DbCommand>,
+    config: &ServerConfig,
+    peer_id: u32,
+    world_name: &str,
+    door_id: Option<&str>,
+) {
+    let world_name = match crate::game::world_names::validate(world_name) {
+        Ok(name) => name,
+        Err(e) => {
+            send_failed_to_enter(host, peer_id, &e.message());
+            return;
+        }
+    };
+    let player_obj = match state.sessions.player(peer_id) {
+        Some(p) => p.clone(),
+        None => { host.disconnect_later_peer(peer_id, 0).ok(); return; }
+    };
+
+
+    let current_world = if let Some(w) = state.active_worlds.get(&world_name) {
+        w.clone()
+    } else {
+        match world::load_world(&world_name) {
+            Ok(Some(w)) => { state.active_worlds.insert(world_name.clone(), w.clone()); w },
+            Ok(None) => {
+                let nw = config.worldgen.create_world(&world_name);
+                db_tx.send(DbCommand::UpdateWorld(nw.clone())).ok();
+                state.active_worlds.insert(world_name.clone(), nw.clone());
+                nw
+            }
+            Err(e) => {
+                error!("Failed to load world {}: {}", world_name, e);
+                send_failed_to_enter(host, peer_id, "`4Unable to enter that world right now.``");
+                return;
+            }
+        }
+    };
+
+
+    if let Some(required) = crate::game::world_settings::level_too_low(&current_world, &player_obj.name, player_obj.level) {
+        send_failed_to_enter(host, peer_id, &format!("`4You need to be level {} to enter this world.``", required));
+        return;
+    }
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    if let Some(expires_at) = current_world.ban_expiry(&player_obj.name, now).filter(|_| !current_world.has_access(&player_obj.name)) {
+        let remaining = if expires_at == 0 { "forever".to_string() } else { crate::game::farming::format_duration(expires_at - now) };
+        send_failed_to_enter(host, peer_id, &format!("`4Oh no!`` You've been banned from that world by its owner! Try again later after the world ban wears off ({}).", remaining));
+        return;
+    }
+
+    let is_new_visit = state.sessions.world(peer_id) != Some(world_name.as_str());
+    if is_new_visit && state.sessions.world(peer_id).is_some() {
+        broadcast_on_remove(host, peer_id, &state.sessions);
+    }
+    state.join_world(peer_id, &world_name);
+
+    if is_new_visit {
+        if let Some(world) = state.active_worlds.get_mut(&world_name) {
+            world.visits += 1;
+            db_tx.send(DbCommand::UpdateWorld(world.clone())).ok();
+        }
+        if let Some(player) = state.sessions.player_mut(peer_id) {
+            player.visit_world(&world_name);
+            db_tx.send(DbCommand::UpdatePlayer(player.clone())).ok();
+        }
+    }
+
+
+    if !current_world.owner_name.is_empty() {
+        send_console_message(host, peer_id, &format!("[`2World Locked by {}`3]", current_world.owner_name));
+    }
+
+
+    let map_pkt = GamePacket::send_map_data();
+    send_packet(host, peer_id, map_pkt.to_bytes_with_raw_data(&current_world.to_bytes()));
+
+
+    let spawn_tile = door_id
+        .and_then(|id| current_world.find_door(id))
+        .or_else(|| current_world.main_door());
+    let (spawn_x, spawn_y) = match spawn_tile {
+        Some(i) => {
+            let (x, y) = current_world.tile_pos(i);
+            ((x * 32) as f32, (y * 32) as f32)
+        }
+        None => (1000.0, 1000.0),
+    };
+    state.sessions.entry(peer_id).pos = (spawn_x, spawn_y);
+
+
+    let local_spawn = format_spawn_avatar(peer_id, peer_id as i32, spawn_x, spawn_y, &player_obj.name, true);
+    let (ls_data, ls_c) = VariantListBuilder::new()
+        .add_string("OnSpawn").add_string(&local_spawn).build();
+    send_variant(host, peer_id, ls_data, ls_c, -1, -1);
+
+
+    let (ox, oy) = (spawn_x, spawn_y);
+    let p_id = calculate_punch_id(&player_obj);
+    send_on_set_clothing(host, peer_id, peer_id, &player_obj, 100);
+    send_set_character_state(host, peer_id, peer_id as i32, ox, oy, p_id);
+
+
+
+    let mut other_count = 0;
+    for other_peer in state.sessions.peers_in_world(&world_name) {
+        if other_peer == peer_id { continue; }
+        other_count += 1;
+
+        let other_p = state.sessions.player(other_peer).cloned().unwrap_or_else(|| player::Player::new("Unknown"));
+        let (ox, oy) = state.sessions.pos(other_peer).unwrap_or((spawn_x, spawn_y));
+
+
+        let ex_spawn = format_spawn_avatar(other_peer, other_peer as i32, ox, oy, &other_p.name, false);
+        let (ex_data, ex_c) = VariantListBuilder::new()
+            .add_string("OnSpawn").add_string(&ex_spawn).build();
+        send_variant(host, peer_id, ex_data, ex_c, -1, -1);
+
+        let o_p_id = calculate_punch_id(&other_p);
+        send_on_set_clothing(host, peer_id, other_peer, &other_p, 100);
+        send_set_character_state(host, peer_id, other_peer as i32, ox, oy, o_p_id);
+
+
+
+        let join_spawn = format_spawn_avatar(peer_id, peer_id as i32, spawn_x, spawn_y, &player_obj.name, false);
+        let (js_data, js_c) = VariantListBuilder::new()
+            .add_string("OnSpawn").add_string(&join_spawn).build();
+        send_variant(host, other_peer, js_data, js_c, -1, -1);
+
+        let p_id = calculate_punch_id(&player_obj);
+        send_on_set_clothing(host, other_peer, peer_id, &player_obj, 100);
+        send_set_character_state(host, other_peer, peer_id as i32, spawn_x, spawn_y, p_id);
+
+    }
+
+    send_inventory(host, peer_id, &player_obj);
+
+    send_console_message(host, peer_id, &format!("World `w{}`` entered. `w{}`` others here.", world_name, other_count));
+    crate::game::world_settings::send_on_entry(host, &current_world, peer_id);
+}
+
+pub fn refresh_world(host: &mut Host, state: &GameState, world_name: &str) {
+    let Some(world) = state.active_worlds.get(world_name) else { return; };
+    let map = GamePacket::send_map_data().to_bytes_with_raw_data(&world.to_bytes());
+    let peers = state.sessions.peers_in_world(world_name);
+
+    for &peer_id in &peers {
+        send_packet(host, peer_id, map.clone());
+        for &other_peer in &peers {
+            let Some(other_p) = state.sessions.player(other_peer) else { continue; };
+            let (ox, oy) = state.sessions.pos(other_peer).unwrap_or((1000.0, 1000.0));
+
+            let spawn = format_spawn_avatar(other_peer, other_peer as i32, ox, oy, &other_p.name, other_peer == peer_id);
+            let (s_data, s_c) = VariantListBuilder::new()
+                .add_string("OnSpawn").add_string(&spawn).build();
+            send_variant(host, peer_id, s_data, s_c, -1, -1);
+
+            send_on_set_clothing(host, peer_id, other_peer, other_p, 100);
+            send_set_character_state(host, peer_id, other_peer as i32, ox, oy, calculate_punch_id(other_p));
+        }
+    }
+}
+
+pub fn send_failed_to_enter(host: &mut Host, peer_id: u32, message: &str) {
+    send_console_message(host, peer_id, message);
+    let (f_data, f_c) = VariantListBuilder::new()
+        .add_string("OnFailedToEnterWorld").add_int(1).build();
+    send_variant(host, peer_id, f_data, f_c, -1, 0);
+}
+
+fn handle_quit(ctx: &mut ActionContext<'_>, _text: &TextPacket) {
+    let peer_id = ctx.peer_id;
+    if ctx.state.sessions.world(peer_id).is_some() {
+
+        broadcast_on_remove(ctx.host, peer_id, &ctx.state.sessions);
+        ctx.state.leave_world(peer_id);
+        send_world_select_menu(ctx.host, ctx.state, ctx.config, peer_id);
+    }
+}
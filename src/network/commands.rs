@@ -1,18 +1,54 @@
 use crate::network::host::Host;
-use crate::database::player::Player;
-use crate::network::packet::{GamePacket, VariantListBuilder};
+use crate::game::state::GameState;
+use crate::network::handler::{ActionContext, ActionRegistry};
+use crate::network::packet::{GamePacket, TextPacket, VariantListBuilder};
+use crate::network::server::broadcast_to_world;
 use tracing::info;
 
-pub fn handle_command(
-    host: &mut Host,
-    peer_id: u32,
-    player: &Player,
-    command_text: &str,
-    peer_worlds: &mut std::collections::HashMap<u32, String>,
-    peer_names: &mut std::collections::HashMap<u32, String>,
-    peer_pos: &mut std::collections::HashMap<u32, (f32, f32)>,
-    peer_hidden_players: &mut std::collections::HashSet<u32>,
-) {
+pub fn register_handlers(registry: &mut ActionRegistry) {
+    registry.register_action("input", handle_input);
+}
+
+
+fn handle_input(ctx: &mut ActionContext<'_>, text: &TextPacket) {
+    let peer_id = ctx.peer_id;
+    if let Some(text) = text.get("text") {
+        if let Some(command_text) = text.strip_prefix('/') {
+            let parts: Vec<&str> = command_text.split_whitespace().collect();
+            if let Some(cmd) = parts.first()
+                && (crate::game::moderation::handle_command(ctx, &cmd.to_lowercase(), &parts[1..])
+                    || crate::game::history::handle_command(ctx, &cmd.to_lowercase(), &parts[1..])
+                    || crate::game::world_edit::handle_command(ctx, &cmd.to_lowercase(), &parts[1..])
+                    || crate::game::world_transfer::handle_command(ctx, &cmd.to_lowercase(), &parts[1..])) {
+                return;
+            }
+            handle_command(ctx.host, ctx.state, peer_id, command_text);
+        } else {
+            if let (Some(w_name), Some(name)) = (ctx.state.sessions.world(peer_id), ctx.state.sessions.name(peer_id)) {
+                let talk_bubble = format!("CP:0_PL:0_OID:_player_chat={}", text);
+                let console = format!("<`w{}``> {}", name, text);
+
+                let pkt = GamePacket::call_function(-1, 0);
+
+                let (b_data, b_c) = VariantListBuilder::new()
+                    .add_string("OnTalkBubble").add_int(peer_id as i32).add_string(&talk_bubble).build();
+                let b_bytes = pkt.to_bytes(&b_data, b_c);
+
+                let (c_data, c_c) = VariantListBuilder::new()
+                    .add_string("OnConsoleMessage").add_string(&console).build();
+                let c_bytes = pkt.to_bytes(&c_data, c_c);
+
+                broadcast_to_world(ctx.host, &ctx.state.sessions, w_name, &b_bytes, None, false);
+                broadcast_to_world(ctx.host, &ctx.state.sessions, w_name, &c_bytes, None, false);
+            }
+        }
+    }
+}
+
+pub fn handle_command(host: &mut Host, state: &mut GameState, peer_id: u32, command_text: &str) {
+    let Some(player) = state.sessions.player(peer_id).cloned() else { return; };
+    let player = &player;
+
     let parts: Vec<&str> = command_text.split_whitespace().collect();
     if parts.is_empty() { return; }
 
@@ -23,7 +59,10 @@ pub fn handle_command(
 
     match cmd.as_str() {
         "help" | "?" => {
-            send_console_msg(host, peer_id, "`wAvailable Commands: ``/help, /stats, /hideplayers, /showplayers, /nick <name>");
+            send_console_msg(host, peer_id, "`wAvailable Commands: ``/help, /stats, /hideplayers, /showplayers, /nick <name>, /kick, /pull, /ban <name> [time], /unban, /unaccess, /history [x y], /rollback <name> <minutes>");
+            if player.is_admin() {
+                send_console_msg(host, peer_id, "`wWorld edit: ``/pos1 [x y], /pos2 [x y], /fill <item>, /replace <from> <to>, /copy, /paste, /undo, /schem <save|load> <name>, /exportworld [json|schem], /importworld <file> <world>");
+            }
         }
         "stats" => {
             let stats_msg = format!("`wStats for {}: ``Level: `w{}``, XP: `w{}``, Gems: `w{}``",
@@ -43,17 +82,21 @@ pub fn handle_command(
 
             send_console_msg(host, peer_id, &format!("`wEquipped (Idx:ID): ``[{}]", equipped_str));
             send_console_msg(host, peer_id, &format!("`wInventory: ``[{}]", slots_str));
+            send_console_msg(host, peer_id, &format!(
+                "`wWorlds: ``{} active, {} loaded",
+                state.sessions.occupied_world_count(),
+                state.active_worlds.len()
+            ));
         }
         "hideplayers" => {
-            peer_hidden_players.insert(peer_id);
+            state.sessions.entry(peer_id).hide_players = true;
 
 
-            let mut remove = GamePacket::new();
-            remove.packet_type = 1;
+            let remove = GamePacket::call_function(-1, 0);
 
-            if let Some(world_name) = peer_worlds.get(&peer_id) {
-                for (&other_peer, other_world) in peer_worlds.iter() {
-                    if other_peer == peer_id || other_world != world_name {
+            if let Some(world_name) = state.sessions.world(peer_id) {
+                for other_peer in state.sessions.peers_in_world(world_name) {
+                    if other_peer == peer_id {
                         continue;
                     }
                     let (rem_data, rem_count) = VariantListBuilder::new()
@@ -67,19 +110,18 @@ pub fn handle_command(
             send_console_msg(host, peer_id, "Other players are now `4hidden``. Type `w/showplayers`` to see them again.");
         }
         "showplayers" => {
-            peer_hidden_players.remove(&peer_id);
+            state.sessions.entry(peer_id).hide_players = false;
 
 
-            if let Some(world_name) = peer_worlds.get(&peer_id) {
-                let mut spawn = GamePacket::new();
-                spawn.packet_type = 1;
+            if let Some(world_name) = state.sessions.world(peer_id) {
+                let spawn = GamePacket::call_function(-1, 0);
 
-                for (&other_peer, other_world) in peer_worlds.iter() {
-                    if other_peer == peer_id || other_world != world_name {
+                for other_peer in state.sessions.peers_in_world(world_name) {
+                    if other_peer == peer_id {
                         continue;
                     }
-                    let other_name = peer_names.get(&other_peer).cloned().unwrap_or_else(|| "Unknown".to_string());
-                    let (ox, oy) = peer_pos.get(&other_peer).cloned().unwrap_or((1000.0, 1000.0));
+                    let other_name = state.sessions.name(other_peer).unwrap_or("Unknown");
+                    let (ox, oy) = state.sessions.pos(other_peer).unwrap_or((1000.0, 1000.0));
 
                     let other_spawn_text = format!(
                         "spawn|avatar\nnetID|{}\nuserID|{}\ncolrect|0|0|20|30\nposXY|{}|{}\nname|`w{}``\ncountry|tr\ninvis|0\nmstate|0\nsmstate|0\nonlineID|\n",
@@ -106,16 +148,14 @@ pub fn handle_command(
             let menu = crate::game::gui::build_role_menu(player, "roleTab_human", peer_id as i32);
             let (d_data, d_c) = VariantListBuilder::new()
                 .add_string("OnDialogRequest").add_string(&menu).build();
-            let mut packet = GamePacket::new();
-            packet.packet_type = 1;
+            let packet = GamePacket::call_function(-1, 0);
             host.send(peer_id, &packet.to_bytes(&d_data, d_c), 0).ok();
         }
         "farmer" => {
             let menu = crate::game::gui::build_farmer_menu(player, peer_id as i32);
             let (d_data, d_c) = VariantListBuilder::new()
                 .add_string("OnDialogRequest").add_string(&menu).build();
-            let mut packet = GamePacket::new();
-            packet.packet_type = 1;
+            let packet = GamePacket::call_function(-1, 0);
             host.send(peer_id, &packet.to_bytes(&d_data, d_c), 0).ok();
         }
         _ => {
@@ -125,8 +165,7 @@ pub fn handle_command(
 }
 
 fn send_console_msg(host: &mut Host, peer_id: u32, message: &str) {
-    let mut msg = GamePacket::new();
-    msg.packet_type = 1;
+    let msg = GamePacket::call_function(-1, 0);
     let (data, count) = VariantListBuilder::new()
         .add_string("OnConsoleMessage")
         .add_string(message)
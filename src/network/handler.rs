@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::sync::mpsc::Sender;
+use tracing::warn;
+use crate::database::db_thread::DbCommand;
+use crate::game::state::GameState;
+use crate::network::host::Host;
+use crate::network::packet::TextPacket;
+use crate::network::server::ServerConfig;
+
+pub struct ActionContext<'a> {
+    pub host: &'a mut Host,
+    pub state: &'a mut GameState,
+    pub db_tx: &'a Sender<DbCommand>,
+    pub config: &'a ServerConfig,
+    pub peer_id: u32,
+}
+
+pub trait ActionHandler {
+    fn handle(&self, ctx: &mut ActionContext<'_>, packet: &TextPacket);
+}
+
+impl<F> ActionHandler for F
+where
+    F: Fn(&mut ActionContext<'_>, &TextPacket),
+{
+    fn handle(&self, ctx: &mut ActionContext<'_>, packet: &TextPacket) {
+        self(ctx, packet)
+    }
+}
+
+#[derive(Default)]
+pub struct ActionRegistry {
+    actions: HashMap<&'static str, Box<dyn ActionHandler>>,
+    dialogs: HashMap<&'static str, Box<dyn ActionHandler>>,
+}
+
+impl ActionRegistry {
+    pub fn new() -> Self {
+        let mut registry = Self::default();
+        crate::network::actions::register_handlers(&mut registry);
+        crate::network::commands::register_handlers(&mut registry);
+        crate::game::gui::register_handlers(&mut registry);
+        crate::game::door::register_handlers(&mut registry);
+        crate::game::locks::register_handlers(&mut registry);
+        crate::game::inventory::register_handlers(&mut registry);
+        crate::game::moderation::register_handlers(&mut registry);
+        registry
+    }
+
+    pub fn register_action(&mut self, action: &'static str, handler: impl ActionHandler + 'static) {
+        if self.actions.insert(action, Box::new(handler)).is_some() {
+            warn!("Action handler for '{}' registered twice, keeping the last one", action);
+        }
+    }
+
+    pub fn register_dialog(&mut self, dialog_name: &'static str, handler: impl ActionHandler + 'static) {
+        if self.dialogs.insert(dialog_name, Box::new(handler)).is_some() {
+            warn!("Dialog handler for '{}' registered twice, keeping the last one", dialog_name);
+        }
+    }
+
+    pub fn dispatch(&self, ctx: &mut ActionContext<'_>, packet: &TextPacket) {
+        let Some(action) = packet.action() else { return; };
+
+        if action == "dialog_return" {
+            let dialog_name = packet.get("dialog_name").unwrap_or_default();
+            match self.dialogs.get(dialog_name) {
+                Some(handler) => handler.handle(ctx, packet),
+                None => warn!("Unhandled dialog_return '{}' from peer {}", dialog_name, ctx.peer_id),
+            }
+            return;
+        }
+
+        match self.actions.get(action) {
+            Some(handler) => handler.handle(ctx, packet),
+            None => warn!("Unhandled action '{}' from peer {}", action, ctx.peer_id),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test_support {
+    use std::sync::mpsc::Sender;
+    use std::time::Duration;
+    use crate::database::db_thread::DbCommand;
+    use crate::database::player::Player;
+    use crate::database::world::World;
+    use crate::game::state::GameState;
+    use crate::network::host::Host;
+    use crate::network::packet::TextPacket;
+    use crate::network::server::ServerConfig;
+    use super::{ActionContext, ActionRegistry};
+
+    pub struct TestServer {
+        pub host: Host,
+        pub state: GameState,
+        pub db_tx: Sender<DbCommand>,
+        pub config: ServerConfig,
+        pub registry: ActionRegistry,
+    }
+
+    impl TestServer {
+        pub fn new() -> Self {
+            let (db_tx, _) = std::sync::mpsc::channel();
+            Self {
+                host: Host::new("127.0.0.1", 0, 8, 2, false, false, None, None, false, false).unwrap(),
+                state: GameState::new(),
+                db_tx,
+                config: ServerConfig {
+                    gs_ip: "127.0.0.1".to_string(),
+                    gs_port: 0,
+                    gs_token: String::new(),
+                    items_dat: Vec::new(),
+                    tick_rate: 20,
+                    world_idle_timeout: Duration::from_secs(60),
+                    worldgen: Default::default(),
+                    block_heal_delay: Duration::from_secs(8),
+                    world_menu: Default::default(),
+                },
+                registry: ActionRegistry::new(),
+            }
+        }
+
+        pub fn join(&mut self, peer_id: u32, player: Player, world: World) {
+            let world_name = world.name.clone();
+            self.state.active_worlds.entry(world_name.clone()).or_insert(world);
+            self.state.connect(peer_id);
+            self.state.sessions.set_name(peer_id, &player.name);
+            self.state.sessions.entry(peer_id).player = Some(player);
+            self.state.join_world(peer_id, &world_name);
+        }
+
+        pub fn world(&self, name: &str) -> &World {
+            &self.state.active_worlds[name]
+        }
+
+        pub fn dispatch(&mut self, peer_id: u32, text: &str) {
+            let packet = TextPacket::parse(text);
+            let mut ctx = ActionContext { host: &mut self.host, state: &mut self.state, db_tx: &self.db_tx, config: &self.config, peer_id };
+            self.registry.dispatch(&mut ctx, &packet);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::test_support::TestServer;
+
+    fn mark(label: &'static str) -> impl Fn(&mut ActionContext<'_>, &TextPacket) {
+        move |ctx, _| ctx.state.sessions.set_name(ctx.peer_id, label)
+    }
+
+    #[test]
+    fn actions_and_dialogs_are_routed() {
+        let mut server = TestServer::new();
+        server.registry = ActionRegistry::default();
+        server.registry.register_action("input", mark("action"));
+        server.registry.register_action("protocol", mark("first key"));
+        server.registry.register_dialog("door_edit", mark("dialog"));
+        server.state.connect(1);
+
+        server.dispatch(1, "action|input\n|text|hi\n");
+        assert_eq!(server.state.sessions.name(1), Some("action"));
+
+        server.dispatch(1, "action|dialog_return\ndialog_name|door_edit\ntilex|1|\ntiley|2|\n");
+        assert_eq!(server.state.sessions.name(1), Some("dialog"));
+
+        server.dispatch(1, "protocol|225\nltoken|abc\n");
+        assert_eq!(server.state.sessions.name(1), Some("first key"));
+    }
+
+    #[test]
+    fn unknown_actions_and_dialogs_are_ignored() {
+        let mut server = TestServer::new();
+        server.registry = ActionRegistry::default();
+        server.registry.register_action("input", mark("action"));
+        server.state.connect(1);
+
+        server.dispatch(1, "action|dialog_return\ndialog_name|input\n");
+        server.dispatch(1, "action|nope\n");
+        server.dispatch(1, "");
+        assert_eq!(server.state.sessions.name(1), None);
+    }
+}
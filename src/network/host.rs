@@ -101,6 +101,10 @@ impl Host {
 
 
 
+    pub fn flush(&mut self) {
+        self.host.flush();
+    }
+
     pub fn ip_address(&self) -> String {
         self.host.socket().local_addr().map(|a| a.ip().to_string()).unwrap_or_default()
     }
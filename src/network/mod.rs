@@ -5,4 +5,6 @@ pub mod peer;
 pub mod packet;
 pub mod discord;
 pub mod login;
-pub mod commands;
\ No newline at end of file
+pub mod commands;
+pub mod handler;
+pub mod actions;
\ No newline at end of file
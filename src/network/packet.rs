@@ -1,9 +1,107 @@
-
 use bytes::{Buf, BufMut, BytesMut};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GamePacketKind {
+    State,
+    CallFunction,
+    UpdateStatus,
+    TileChange,
+    SendMapData,
+    SendTileUpdateData,
+    TileActivate,
+    TileApplyDamage,
+    SendInventory,
+    ItemActivate,
+    ItemActivateObject,
+    ItemChangeObject,
+    SendItemDatabase,
+    SendParticleEffect,
+    SetCharacterState,
+    PingRequest,
+    Unknown(u8),
+}
+
+impl GamePacketKind {
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            0x00 => Self::State,
+            0x01 => Self::CallFunction,
+            0x02 => Self::UpdateStatus,
+            0x03 => Self::TileChange,
+            0x04 => Self::SendMapData,
+            0x05 => Self::SendTileUpdateData,
+            0x07 => Self::TileActivate,
+            0x08 => Self::TileApplyDamage,
+            0x09 => Self::SendInventory,
+            0x0A => Self::ItemActivate,
+            0x0B => Self::ItemActivateObject,
+            0x0E => Self::ItemChangeObject,
+            0x10 => Self::SendItemDatabase,
+            0x11 => Self::SendParticleEffect,
+            0x14 => Self::SetCharacterState,
+            0x16 => Self::PingRequest,
+            other => Self::Unknown(other),
+        }
+    }
+
+    pub fn as_u8(self) -> u8 {
+        match self {
+            Self::State => 0x00,
+            Self::CallFunction => 0x01,
+            Self::UpdateStatus => 0x02,
+            Self::TileChange => 0x03,
+            Self::SendMapData => 0x04,
+            Self::SendTileUpdateData => 0x05,
+            Self::TileActivate => 0x07,
+            Self::TileApplyDamage => 0x08,
+            Self::SendInventory => 0x09,
+            Self::ItemActivate => 0x0A,
+            Self::ItemActivateObject => 0x0B,
+            Self::ItemChangeObject => 0x0E,
+            Self::SendItemDatabase => 0x10,
+            Self::SendParticleEffect => 0x11,
+            Self::SetCharacterState => 0x14,
+            Self::PingRequest => 0x16,
+            Self::Unknown(other) => other,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CharacterState {
+    pub punch_id: u8,
+    pub build_range: u8,
+    pub punch_range: u8,
+    pub speed: f32,
+    pub gravity: f32,
+    pub water_speed: f32,
+    pub pupil_color: u32,
+    pub hair_color: u32,
+    pub eye_color: u32,
+}
+
+impl Default for CharacterState {
+    fn default() -> Self {
+        Self {
+            punch_id: 0,
+            build_range: 0x80,
+            punch_range: 0x80,
+            speed: 250.0,
+            gravity: 1000.0,
+            water_speed: 125.0,
+            pupil_color: 0x000000FF,
+            hair_color: 0xFFFFFFFF,
+            eye_color: 0xFFFFFFFF,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct GamePacket {
-    pub packet_type: i32,
+    pub kind: GamePacketKind,
+    pub object_type: u8,
+    pub count1: u8,
+    pub count2: u8,
     pub net_id: i32,
     pub uid: i32,
     pub peer_state: i32,
@@ -21,7 +119,10 @@ pub struct GamePacket {
 impl GamePacket {
     pub fn new() -> Self {
         Self {
-            packet_type: 0,
+            kind: GamePacketKind::State,
+            object_type: 0,
+            count1: 0,
+            count2: 0,
             net_id: -1,
             uid: -1,
             peer_state: 8,
@@ -37,6 +138,147 @@ impl GamePacket {
         }
     }
 
+    pub fn with_kind(kind: GamePacketKind) -> Self {
+        Self { kind, ..Self::new() }
+    }
+
+    pub fn call_function(net_id: i32, delay: i32) -> Self {
+        let mut pkt = Self::with_kind(GamePacketKind::CallFunction);
+        pkt.net_id = net_id;
+        pkt.id = delay;
+        pkt
+    }
+
+    pub fn state(net_id: i32, x: f32, y: f32) -> Self {
+        let mut pkt = Self::with_kind(GamePacketKind::State);
+        pkt.net_id = net_id;
+        pkt.pos_x = x;
+        pkt.pos_y = y;
+        pkt.peer_state = 0;
+        pkt
+    }
+
+    pub fn tile_change(net_id: i32, tile_x: i32, tile_y: i32, item_id: i32) -> Self {
+        let mut pkt = Self::with_kind(GamePacketKind::TileChange);
+        pkt.net_id = net_id;
+        pkt.punch_x = tile_x;
+        pkt.punch_y = tile_y;
+        pkt.id = item_id;
+        pkt
+    }
+
+    pub fn tile_apply_damage(net_id: i32, tile_x: i32, tile_y: i32, hits: u8) -> Self {
+        let mut pkt = Self::with_kind(GamePacketKind::TileApplyDamage);
+        pkt.net_id = net_id;
+        pkt.punch_x = tile_x;
+        pkt.punch_y = tile_y;
+        pkt.id = 6;
+        pkt.count = hits as f32;
+        pkt
+    }
+
+    pub fn particle_effect(net_id: i32, x: f32, y: f32, particle_id: i32) -> Self {
+        let mut pkt = Self::with_kind(GamePacketKind::SendParticleEffect);
+        pkt.net_id = net_id;
+        pkt.pos_x = x;
+        pkt.pos_y = y;
+        pkt.speed_y = particle_id as f32;
+        pkt
+    }
+
+    pub fn set_character_state(net_id: i32, x: f32, y: f32, state: &CharacterState) -> Self {
+        let mut pkt = Self::with_kind(GamePacketKind::SetCharacterState);
+        pkt.object_type = state.punch_id;
+        pkt.count1 = state.build_range;
+        pkt.count2 = state.punch_range;
+        pkt.net_id = net_id;
+        pkt.peer_state = 0;
+        pkt.pos_x = x;
+        pkt.pos_y = y;
+        pkt.speed_x = state.speed;
+        pkt.speed_y = state.gravity;
+        pkt.count = state.water_speed;
+        pkt.uid = state.pupil_color as i32;
+        pkt.punch_x = state.hair_color as i32;
+        pkt.punch_y = state.eye_color as i32;
+        pkt
+    }
+
+    pub fn send_inventory(net_id: i32) -> Self {
+        let mut pkt = Self::with_kind(GamePacketKind::SendInventory);
+        pkt.net_id = net_id;
+        pkt.peer_state = 0x08;
+        pkt
+    }
+
+    pub fn send_tile_update(tile_x: i32, tile_y: i32) -> Self {
+        let mut pkt = Self::with_kind(GamePacketKind::SendTileUpdateData);
+        pkt.peer_state = 0x08;
+        pkt.punch_x = tile_x;
+        pkt.punch_y = tile_y;
+        pkt
+    }
+
+    pub fn spawn_object(item_id: u16, x: f32, y: f32, count: u8) -> Self {
+        let mut pkt = Self::with_kind(GamePacketKind::ItemChangeObject);
+        pkt.id = item_id as i32;
+        pkt.pos_x = x;
+        pkt.pos_y = y;
+        pkt.count = count as f32;
+        pkt
+    }
+
+    pub fn collect_object(net_id: i32, uid: u32) -> Self {
+        let mut pkt = Self::with_kind(GamePacketKind::ItemChangeObject);
+        pkt.net_id = net_id;
+        pkt.id = uid as i32;
+        pkt
+    }
+
+    pub fn modify_object(uid: u32, item_id: u16, x: f32, y: f32, count: u8) -> Self {
+        let mut pkt = Self::with_kind(GamePacketKind::ItemChangeObject);
+        pkt.net_id = -3;
+        pkt.uid = uid as i32;
+        pkt.id = item_id as i32;
+        pkt.pos_x = x;
+        pkt.pos_y = y;
+        pkt.count = count as f32;
+        pkt
+    }
+
+    pub fn send_map_data() -> Self {
+        Self::with_kind(GamePacketKind::SendMapData)
+    }
+
+    pub fn send_item_database() -> Self {
+        Self::with_kind(GamePacketKind::SendItemDatabase)
+    }
+
+    pub fn ping_request() -> Self {
+        Self::with_kind(GamePacketKind::PingRequest)
+    }
+
+    fn header_word(&self) -> i32 {
+        i32::from_le_bytes([self.kind.as_u8(), self.object_type, self.count1, self.count2])
+    }
+
+    fn put_header(&self, buf: &mut BytesMut) {
+        buf.put_i32_le(4);
+        buf.put_i32_le(self.header_word());
+        buf.put_i32_le(self.net_id);
+        buf.put_i32_le(self.uid);
+        buf.put_i32_le(self.peer_state);
+        buf.put_f32_le(self.count);
+        buf.put_i32_le(self.id);
+        buf.put_f32_le(self.pos_x);
+        buf.put_f32_le(self.pos_y);
+        buf.put_f32_le(self.speed_x);
+        buf.put_f32_le(self.speed_y);
+        buf.put_i32_le(self.idk);
+        buf.put_i32_le(self.punch_x);
+        buf.put_i32_le(self.punch_y);
+    }
+
     pub fn from_bytes(data: &[u8]) -> Option<(Self, Vec<u8>)> {
         if data.len() < 60 { return None; }
 
@@ -45,8 +287,12 @@ impl GamePacket {
         let header = rdr.get_i32_le();
         if header != 4 && header != 10 { return None; }
 
+        let [kind, object_type, count1, count2] = rdr.get_i32_le().to_le_bytes();
         let packet = Self {
-            packet_type: rdr.get_i32_le(),
+            kind: GamePacketKind::from_u8(kind),
+            object_type,
+            count1,
+            count2,
             net_id: rdr.get_i32_le(),
             uid: rdr.get_i32_le(),
             peer_state: rdr.get_i32_le(),
@@ -73,20 +319,7 @@ impl GamePacket {
     pub fn to_bytes(&self, variant_data: &[u8], variant_count: u8) -> Vec<u8> {
         let mut buf = BytesMut::with_capacity(60 + variant_data.len());
 
-        buf.put_i32_le(4);
-        buf.put_i32_le(self.packet_type);
-        buf.put_i32_le(self.net_id);
-        buf.put_i32_le(self.uid);
-        buf.put_i32_le(self.peer_state);
-        buf.put_f32_le(self.count);
-        buf.put_i32_le(self.id);
-        buf.put_f32_le(self.pos_x);
-        buf.put_f32_le(self.pos_y);
-        buf.put_f32_le(self.speed_x);
-        buf.put_f32_le(self.speed_y);
-        buf.put_i32_le(self.idk);
-        buf.put_i32_le(self.punch_x);
-        buf.put_i32_le(self.punch_y);
+        self.put_header(&mut buf);
 
         if variant_count > 0 {
             buf.put_u32_le(variant_data.len() as u32 + 1);
@@ -102,20 +335,7 @@ impl GamePacket {
     pub fn to_bytes_with_raw_data(&self, raw_data: &[u8]) -> Vec<u8> {
         let mut buf = BytesMut::with_capacity(60 + raw_data.len());
 
-        buf.put_i32_le(4);
-        buf.put_i32_le(self.packet_type);
-        buf.put_i32_le(self.net_id);
-        buf.put_i32_le(self.uid);
-        buf.put_i32_le(self.peer_state);
-        buf.put_f32_le(self.count);
-        buf.put_i32_le(self.id);
-        buf.put_f32_le(self.pos_x);
-        buf.put_f32_le(self.pos_y);
-        buf.put_f32_le(self.speed_x);
-        buf.put_f32_le(self.speed_y);
-        buf.put_i32_le(self.idk);
-        buf.put_i32_le(self.punch_x);
-        buf.put_i32_le(self.punch_y);
+        self.put_header(&mut buf);
 
         buf.put_u32_le(raw_data.len() as u32);
         buf.put_slice(raw_data);
@@ -124,73 +344,439 @@ impl GamePacket {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum Variant {
+    Float(f32),
+    String(String),
+    Vec2(f32, f32),
+    Vec3(f32, f32, f32),
+    UInt(u32),
+    Int(i32),
+}
+
+impl Variant {
+    pub fn type_id(&self) -> u8 {
+        match self {
+            Variant::Float(_) => 0x01,
+            Variant::String(_) => 0x02,
+            Variant::Vec2(..) => 0x03,
+            Variant::Vec3(..) => 0x04,
+            Variant::UInt(_) => 0x05,
+            Variant::Int(_) => 0x09,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Variant::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn write(&self, buf: &mut Vec<u8>) {
+        match self {
+            Variant::Float(v) => buf.put_f32_le(*v),
+            Variant::String(v) => {
+                buf.put_i32_le(v.len() as i32);
+                buf.put_slice(v.as_bytes());
+            }
+            Variant::Vec2(x, y) => {
+                buf.put_f32_le(*x);
+                buf.put_f32_le(*y);
+            }
+            Variant::Vec3(x, y, z) => {
+                buf.put_f32_le(*x);
+                buf.put_f32_le(*y);
+                buf.put_f32_le(*z);
+            }
+            Variant::UInt(v) => buf.put_u32_le(*v),
+            Variant::Int(v) => buf.put_i32_le(*v),
+        }
+    }
+
+    fn read(type_id: u8, rdr: &mut &[u8]) -> Option<Self> {
+        let variant = match type_id {
+            0x01 => {
+                if rdr.remaining() < 4 { return None; }
+                Variant::Float(rdr.get_f32_le())
+            }
+            0x02 => {
+                if rdr.remaining() < 4 { return None; }
+                let len = usize::try_from(rdr.get_i32_le()).ok()?;
+                if rdr.remaining() < len { return None; }
+                let s = String::from_utf8_lossy(&rdr[..len]).into_owned();
+                rdr.advance(len);
+                Variant::String(s)
+            }
+            0x03 => {
+                if rdr.remaining() < 8 { return None; }
+                Variant::Vec2(rdr.get_f32_le(), rdr.get_f32_le())
+            }
+            0x04 => {
+                if rdr.remaining() < 12 { return None; }
+                Variant::Vec3(rdr.get_f32_le(), rdr.get_f32_le(), rdr.get_f32_le())
+            }
+            0x05 => {
+                if rdr.remaining() < 4 { return None; }
+                Variant::UInt(rdr.get_u32_le())
+            }
+            0x09 => {
+                if rdr.remaining() < 4 { return None; }
+                Variant::Int(rdr.get_i32_le())
+            }
+            _ => return None,
+        };
+        Some(variant)
+    }
+}
+
+impl std::fmt::Display for Variant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Variant::Float(v) => write!(f, "{}", v),
+            Variant::String(v) => write!(f, "{:?}", v),
+            Variant::Vec2(x, y) => write!(f, "({}, {})", x, y),
+            Variant::Vec3(x, y, z) => write!(f, "({}, {}, {})", x, y, z),
+            Variant::UInt(v) => write!(f, "{}", v),
+            Variant::Int(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VariantList {
+    items: Vec<Variant>,
+}
+
+impl VariantList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, variant: Variant) {
+        self.items.push(variant);
+    }
+
+    pub fn function_name(&self) -> Option<&str> {
+        self.items.first().and_then(Variant::as_str)
+    }
+
+    pub fn encode(&self) -> (Vec<u8>, u8) {
+        let mut data = Vec::new();
+        for (i, item) in self.items.iter().enumerate() {
+            data.push(i as u8);
+            data.push(item.type_id());
+            item.write(&mut data);
+        }
+        (data, self.items.len() as u8)
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        let mut rdr = data;
+        if rdr.remaining() < 1 { return None; }
+        let count = rdr.get_u8();
+
+        let mut items = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            if rdr.remaining() < 2 { return None; }
+            let _index = rdr.get_u8();
+            let type_id = rdr.get_u8();
+            items.push(Variant::read(type_id, &mut rdr)?);
+        }
+        Some(Self { items })
+    }
+}
+
+impl std::fmt::Display for VariantList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let args = match self.function_name() {
+            Some(name) => {
+                write!(f, "{}", name)?;
+                &self.items[1..]
+            }
+            None => &self.items[..],
+        };
+        write!(f, "(")?;
+        for (i, arg) in args.iter().enumerate() {
+            if i > 0 { write!(f, ", ")?; }
+            write!(f, "{}", arg)?;
+        }
+        write!(f, ")")
+    }
+}
+
 pub struct VariantListBuilder {
-    data: Vec<u8>,
-    count: u8,
+    list: VariantList,
 }
 
 impl VariantListBuilder {
     pub fn new() -> Self {
         Self {
-            data: Vec::new(),
-            count: 0,
+            list: VariantList::new(),
         }
     }
 
-    pub fn add_string(mut self, val: &str) -> Self {
-        self.data.push(self.count);
-        self.data.push(0x02);
-        self.data.put_i32_le(val.len() as i32);
-        self.data.put_slice(val.as_bytes());
-        self.count += 1;
+    pub fn add(mut self, variant: Variant) -> Self {
+        self.list.push(variant);
         self
     }
 
-    pub fn add_int(mut self, val: i32) -> Self {
-        self.data.push(self.count);
-        self.data.push(0x09);
-        self.data.put_i32_le(val);
-        self.count += 1;
-        self
+    pub fn add_string(self, val: &str) -> Self {
+        self.add(Variant::String(val.to_string()))
     }
 
-    pub fn add_uint(mut self, val: u32) -> Self {
-        self.data.push(self.count);
-        self.data.push(0x05);
-        self.data.put_u32_le(val);
-        self.count += 1;
-        self
+    pub fn add_int(self, val: i32) -> Self {
+        self.add(Variant::Int(val))
     }
 
-
-    pub fn add_float(mut self, val: f32) -> Self {
-        self.data.push(self.count);
-        self.data.push(0x01);
-        self.data.put_f32_le(val);
-        self.count += 1;
-        self
+    pub fn add_uint(self, val: u32) -> Self {
+        self.add(Variant::UInt(val))
     }
 
-    pub fn add_vec2(mut self, x: f32, y: f32) -> Self {
-        self.data.push(self.count);
-        self.data.push(0x03);
-        self.data.put_f32_le(x);
-        self.data.put_f32_le(y);
-        self.count += 1;
-        self
+    pub fn add_vec2(self, x: f32, y: f32) -> Self {
+        self.add(Variant::Vec2(x, y))
     }
 
-    pub fn add_vec3(mut self, x: f32, y: f32, z: f32) -> Self {
-        self.data.push(self.count);
-        self.data.push(0x04);
-        self.data.put_f32_le(x);
-        self.data.put_f32_le(y);
-        self.data.put_f32_le(z);
-        self.count += 1;
-        self
+    pub fn add_vec3(self, x: f32, y: f32, z: f32) -> Self {
+        self.add(Variant::Vec3(x, y, z))
     }
 
     pub fn build(self) -> (Vec<u8>, u8) {
-        (self.data, self.count)
+        self.build_list().encode()
+    }
+
+    pub fn build_list(self) -> VariantList {
+        self.list
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextPacket {
+    fields: Vec<(String, String)>,
+}
+
+impl TextPacket {
+    pub fn parse(text: &str) -> Self {
+        let mut fields = Vec::new();
+        for line in text.split('\n') {
+            let line = line.trim_matches(|c: char| c == '\0' || c == '\r');
+            let line = line.strip_prefix('|').unwrap_or(line);
+            if line.is_empty() { continue; }
+
+            let (key, value) = match line.split_once('|') {
+                Some((k, v)) => (k.trim(), v.trim().trim_end_matches('|').trim_end()),
+                None => (line.trim(), ""),
+            };
+            if key.is_empty() { continue; }
+
+            fields.push((key.to_string(), value.to_string()));
+        }
+        Self { fields }
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Self {
+        if data.len() < 4 { return Self::default(); }
+        Self::parse(&String::from_utf8_lossy(&data[4..]))
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fields.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    pub fn get_parsed<T: std::str::FromStr>(&self, key: &str) -> Option<T> {
+        self.get(key).and_then(|v| v.parse().ok())
+    }
+
+    pub fn checkbox(&self, key: &str) -> Option<bool> {
+        match self.get(key)? {
+            "1" => Some(true),
+            "0" => Some(false),
+            _ => None,
+        }
+    }
+
+    pub fn action(&self) -> Option<&str> {
+        self.get("action").or_else(|| self.fields.first().map(|(k, _)| k.as_str()))
+    }
+}
+
+#[cfg(test)]
+impl TextPacket {
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    pub fn fields(&self) -> &[(String, String)] {
+        &self.fields
+    }
+
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.fields.iter().filter(move |(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variant_list_round_trips_through_game_packet() {
+        let list = VariantListBuilder::new()
+            .add_string("OnSpawn")
+            .add(Variant::Float(1.5))
+            .add_vec2(32.0, 64.0)
+            .add_vec3(1.0, 2.0, 3.0)
+            .add_uint(2816436900)
+            .add_int(-1)
+            .add_string("")
+            .build_list();
+
+        let pkt = GamePacket::call_function(12, 0);
+        let (data, count) = list.encode();
+        let bytes = pkt.to_bytes(&data, count);
+
+        let (decoded_pkt, extra) = GamePacket::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded_pkt.kind, GamePacketKind::CallFunction);
+        assert_eq!(decoded_pkt.net_id, 12);
+        assert_eq!(VariantList::from_bytes(&extra), Some(list));
+    }
+
+    #[test]
+    fn set_character_state_packs_header_bytes() {
+        let state = CharacterState { punch_id: 7, ..CharacterState::default() };
+        let bytes = GamePacket::set_character_state(3, 64.0, 96.0, &state).to_bytes(&[], 0);
+        assert_eq!(&bytes[4..8], &[0x14, 7, 0x80, 0x80]);
+
+        let (decoded, _) = GamePacket::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.kind, GamePacketKind::SetCharacterState);
+        assert_eq!(decoded.object_type, 7);
+        assert_eq!(decoded.speed_x, 250.0);
+        assert_eq!(decoded.speed_y, 1000.0);
+        assert_eq!(decoded.punch_y, -1);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn unknown_kinds_survive_decoding() {
+        for raw in 0..=u8::MAX {
+            assert_eq!(GamePacketKind::from_u8(raw).as_u8(), raw);
+        }
+    }
+
+    #[test]
+    fn variant_list_rejects_truncated_data() {
+        let (data, count) = VariantListBuilder::new().add_string("OnConsoleMessage").add_string("hi").build();
+        let mut raw = vec![count];
+        raw.extend_from_slice(&data);
+
+        assert!(VariantList::from_bytes(&raw).is_some());
+        for len in 0..raw.len() {
+            assert_eq!(VariantList::from_bytes(&raw[..len]), None);
+        }
+        raw[2] = 0x42;
+        assert_eq!(VariantList::from_bytes(&raw), None);
+    }
+
+    #[test]
+    fn variant_list_pretty_prints() {
+        let list = VariantListBuilder::new().add_string("OnConsoleMessage").add_string("Hello \"world\"").build_list();
+        assert_eq!(list.to_string(), r#"OnConsoleMessage("Hello \"world\"")"#);
+
+        let list = VariantListBuilder::new()
+            .add_string("OnParticleEffect").add_int(5).add_vec2(32.0, 64.5).add_uint(7).build_list();
+        assert_eq!(list.function_name(), Some("OnParticleEffect"));
+        assert_eq!(list.to_string(), "OnParticleEffect(5, (32, 64.5), 7)");
+
+        let list = VariantListBuilder::new().add_int(1).build_list();
+        assert_eq!(list.to_string(), "(1)");
+    }
+
+    #[test]
+    fn parses_action_lines() {
+        let pkt = TextPacket::parse("action|join_request\nname|START\ninvitedWorld|0\n");
+        assert_eq!(pkt.action(), Some("join_request"));
+        assert_eq!(pkt.get("name"), Some("START"));
+        assert_eq!(pkt.get_parsed::<i32>("invitedWorld"), Some(0));
+    }
+
+    #[test]
+    fn action_falls_back_to_first_key() {
+        let pkt = TextPacket::parse("protocol|225\nltoken|abc\n");
+        assert_eq!(pkt.action(), Some("protocol"));
+        let pkt = TextPacket::parse("tankIDName|Tester\ntankIDPass|x\n");
+        assert_eq!(pkt.action(), Some("tankIDName"));
+    }
+
+    #[test]
+    fn keeps_pipes_inside_values() {
+        let pkt = TextPacket::parse("action|input\n|text|a|b|c\n");
+        assert_eq!(pkt.get("text"), Some("a|b|c"));
+        let pkt = TextPacket::parse("action|input\ntext|a|b|c\n");
+        assert_eq!(pkt.get("text"), Some("a|b|c"));
+    }
+
+    #[test]
+    fn keeps_empty_values_without_shifting() {
+        let pkt = TextPacket::parse("requestedName|\ntankIDName|Tester\nf|1\n");
+        assert_eq!(pkt.get("requestedName"), Some(""));
+        assert_eq!(pkt.get("tankIDName"), Some("Tester"));
+        assert_eq!(pkt.get("f"), Some("1"));
+    }
+
+    #[test]
+    fn dialog_return_fields() {
+        let pkt = TextPacket::parse(
+            "action|dialog_return\ndialog_name|sign_edit\ntilex|10|\ntiley|20|\nsign_text|hello | world\ncheckbox_public|1\ncheckbox_nodrop|0\nbuttonClicked|save\n\n",
+        );
+        assert_eq!(pkt.action(), Some("dialog_return"));
+        assert_eq!(pkt.get("dialog_name"), Some("sign_edit"));
+        assert_eq!(pkt.get("tilex"), Some("10"));
+        assert_eq!(pkt.get_parsed::<i32>("tiley"), Some(20));
+        assert_eq!(pkt.get("sign_text"), Some("hello | world"));
+        assert_eq!(pkt.checkbox("checkbox_public"), Some(true));
+        assert_eq!(pkt.checkbox("checkbox_nodrop"), Some(false));
+        assert_eq!(pkt.checkbox("sign_text"), None);
+        assert_eq!(pkt.get("buttonClicked"), Some("save"));
+    }
+
+    #[test]
+    fn repeated_keys_are_kept() {
+        let pkt = TextPacket::parse("action|dialog_return\nitem|2\nitem|4\nitem|\n");
+        assert_eq!(pkt.get("item"), Some("2"));
+        assert_eq!(pkt.get_all("item").collect::<Vec<_>>(), vec!["2", "4", ""]);
+    }
+
+    #[test]
+    fn strips_nulls_and_carriage_returns() {
+        let mut raw = vec![2, 0, 0, 0];
+        raw.extend_from_slice(b"action|quit_to_exit\r\n\0");
+        let pkt = TextPacket::from_bytes(&raw);
+        assert_eq!(pkt.action(), Some("quit_to_exit"));
+        assert_eq!(pkt.fields().len(), 1);
+        assert!(TextPacket::from_bytes(&[2, 0]).is_empty());
+    }
+
+    #[test]
+    fn fuzz_random_input_never_panics() {
+        use rand::{Rng, SeedableRng};
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0x6772_6f77);
+        let alphabet = b"ab|\n\r\0 \xff\xc3action";
+
+        for _ in 0..5000 {
+            let len = rng.gen_range(0..64);
+            let mut raw: Vec<u8> = (0..len).map(|_| alphabet[rng.gen_range(0..alphabet.len())]).collect();
+            if rng.gen_bool(0.5) {
+                raw.extend((0..rng.gen_range(0..16)).map(|_| rng.r#gen::<u8>()));
+            }
+
+            let pkt = TextPacket::from_bytes(&raw);
+            for (k, v) in pkt.fields() {
+                assert!(!k.is_empty());
+                assert!(!k.contains('\n') && !v.contains('\n'));
+                assert_eq!(pkt.get_all(k).next(), pkt.get(k));
+            }
+            if !pkt.is_empty() {
+                assert!(pkt.action().is_some());
+            }
+        }
+    }
+}